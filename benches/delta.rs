@@ -39,6 +39,22 @@ fn delta(c: &mut Criterion) {
             Delta::undelta(&unpacked, &[0; 64], &mut undelta);
         });
     });
+
+    group.bench_function("delta u16 fused untranspose", |b| {
+        b.iter(|| {
+            let mut untransposed = [0; 1024];
+            Delta::undelta_pack_untranspose::<W>(&packed, &[0; 64], &mut untransposed);
+        });
+    });
+
+    group.bench_function("delta u16 fused then untranspose", |b| {
+        b.iter(|| {
+            let mut unpacked = [0; 1024];
+            Delta::undelta_pack::<W>(&packed, &[0; 64], &mut unpacked);
+            let mut untransposed = [0; 1024];
+            Transpose::untranspose(&unpacked, &mut untransposed);
+        });
+    });
 }
 
 criterion_group!(benches, delta);
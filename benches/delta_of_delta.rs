@@ -0,0 +1,59 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::mem::size_of;
+
+use fastlanes::{BitPacking, DeltaOfDelta, Transpose};
+
+fn delta_of_delta(c: &mut Criterion) {
+    const W: usize = 2;
+
+    let mut group = c.benchmark_group("delta-of-delta");
+    let mut values: [u64; 1024] = [0; 1024];
+    for i in 0..1024 {
+        values[i] = 1_700_000_000_000 + (i as u64) * 1000 + [0, 1, 0, 0, 0, 0, 1, 0][i % 8];
+    }
+
+    let mut transposed = [0; 1024];
+    Transpose::transpose(&values, &mut transposed);
+
+    // Each lane starts one cadence step after its base.
+    let mut base = [0; 16];
+    for lane in 0..16 {
+        base[lane] = transposed[lane] - 1000;
+    }
+    let base_delta = [1000; 16];
+    let mut deltas = [0; 1024];
+    DeltaOfDelta::delta_of_delta(&transposed, &base, &base_delta, &mut deltas);
+    assert!(deltas.iter().all(|&d| d < (1 << W)));
+
+    let mut packed = [0; 128 * W / size_of::<u64>()];
+    BitPacking::pack::<W>(&deltas, &mut packed);
+
+    // Both paths must decode the input they are measured on.
+    let mut unpacked = [0; 1024];
+    DeltaOfDelta::undelta_of_delta_pack::<W>(&packed, &base, &base_delta, &mut unpacked);
+    assert_eq!(unpacked, transposed);
+    DeltaOfDelta::undelta_of_delta(&deltas, &base, &base_delta, &mut unpacked);
+    assert_eq!(unpacked, transposed);
+
+    group.bench_function("delta-of-delta u64 fused", |b| {
+        b.iter(|| {
+            let mut unpacked = [0; 1024];
+            DeltaOfDelta::undelta_of_delta_pack::<W>(&packed, &base, &base_delta, &mut unpacked);
+        });
+    });
+
+    group.bench_function("delta-of-delta u64 unfused", |b| {
+        b.iter(|| {
+            let mut unpacked = [0; 1024];
+            BitPacking::unpack::<W>(&packed, &mut unpacked);
+            let mut undelta = [0; 1024];
+            DeltaOfDelta::undelta_of_delta(&unpacked, &base, &base_delta, &mut undelta);
+        });
+    });
+}
+
+criterion_group!(benches, delta_of_delta);
+criterion_main!(benches);
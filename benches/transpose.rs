@@ -16,6 +16,56 @@ fn transpose(c: &mut Criterion) {
         let mut transposed = [0; 1024];
         b.iter(|| Transpose::transpose(&values, &mut transposed));
     });
+
+    group.bench_function("transpose_tiled u16", |b| {
+        let values: [u16; 1024] = core::array::from_fn(|i| i as u16);
+
+        let mut transposed = [0; 1024];
+        b.iter(|| Transpose::transpose_tiled(&values, &mut transposed));
+    });
+
+    group.bench_function("transpose u64", |b| {
+        let values: [u64; 1024] = core::array::from_fn(|i| i as u64);
+
+        let mut transposed = [0; 1024];
+        b.iter(|| Transpose::transpose(&values, &mut transposed));
+    });
+
+    group.bench_function("transpose_tiled u64", |b| {
+        let values: [u64; 1024] = core::array::from_fn(|i| i as u64);
+
+        let mut transposed = [0; 1024];
+        b.iter(|| Transpose::transpose_tiled(&values, &mut transposed));
+    });
+
+    group.bench_function("transpose u16 in-place", |b| {
+        let mut values: [u16; 1024] = [0; 1024];
+        for i in 0..1024 {
+            values[i] = (i % u16::MAX as usize) as u16;
+        }
+
+        b.iter(|| Transpose::transpose_in_place(&mut values));
+    });
+
+    group.bench_function("untranspose u64", |b| {
+        let values: [u64; 1024] = core::array::from_fn(|i| i as u64);
+
+        let mut untransposed = [0; 1024];
+        b.iter(|| Transpose::untranspose(&values, &mut untransposed));
+    });
+
+    group.bench_function("untranspose_tiled u64", |b| {
+        let values: [u64; 1024] = core::array::from_fn(|i| i as u64);
+
+        let mut untransposed = [0; 1024];
+        b.iter(|| Transpose::untranspose_tiled(&values, &mut untransposed));
+    });
+
+    group.bench_function("untranspose u64 in-place", |b| {
+        let mut values: [u64; 1024] = core::array::from_fn(|i| i as u64);
+
+        b.iter(|| Transpose::untranspose_in_place(&mut values));
+    });
 }
 
 criterion_group!(benches, transpose);
@@ -0,0 +1,394 @@
+use arrayref::{array_mut_ref, array_ref};
+use core::mem::size_of;
+use paste::paste;
+
+use crate::{
+    seq_t, unpack, BitPackWidth, Constant, FastLanes, FoR, FoRHeader, Patches,
+    SupportedBitPackWidth,
+};
+
+/// The exponent `e` and factor `f` an ALP-encoded chunk is scaled by.
+///
+/// Each value is encoded as the integer `round(value * 10^e * 10^-f)` and decoded as
+/// `encoded * 10^f * 10^-e`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Exponents {
+    pub e: u8,
+    pub f: u8,
+}
+
+/// Adaptive Lossless floating-Point (ALP) encoding.
+///
+/// Decimals stored as floats become small integers once scaled by the right power of ten. These
+/// are FoR-packed, and the few values that do not survive the round trip exactly are stored as
+/// [`Patches`].
+pub trait ALP: Copy + Sized {
+    /// The unsigned integer the encoded values are packed as.
+    type UInt: FoR;
+
+    /// The largest exponent whose power of ten is exactly representable.
+    const MAX_EXPONENT: u8;
+
+    /// Chooses the exponents minimising the packed size plus the size of the exceptions, estimated
+    /// from a sample of the chunk.
+    fn find_exponents(input: &[Self; 1024]) -> Exponents;
+
+    /// Scales each value to an integer, stored as its two's complement bits.
+    ///
+    /// Values that do not decode back to exactly the same bits are returned as patches, and
+    /// replaced in the output by another encoded value so that they do not widen the chunk.
+    fn alp_encode(
+        input: &[Self; 1024],
+        exponents: Exponents,
+        output: &mut [Self::UInt; 1024],
+    ) -> Patches<Self>;
+
+    /// ALP-encodes 1024 elements, choosing the exponents, reference and width itself.
+    fn alp_pack(
+        input: &[Self; 1024],
+        output: &mut [Self::UInt; 1024],
+    ) -> (Exponents, FoRHeader<Self::UInt>, Patches<Self>);
+
+    /// Unpacks 1024 `W` bit integers, adds the reference and scales each back to a float, then
+    /// applies the patches.
+    fn unalp_pack<const W: usize>(
+        input: &[Self::UInt; 1024 * W / <Self::UInt as FastLanes>::T],
+        exponents: Exponents,
+        reference: Self::UInt,
+        patches: &Patches<Self>,
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self::UInt>;
+
+    /// Unpacks and decodes 1024 `W` bit integers, where `W` is runtime-known instead of
+    /// compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of length `1024 * W / T`, where `T` is the bit-width of the
+    /// integers and `W` is the packed width. The output slice must be of exactly length 1024.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_unalp_pack(
+        width: usize,
+        input: &[Self::UInt],
+        exponents: Exponents,
+        reference: Self::UInt,
+        patches: &Patches<Self>,
+        output: &mut [Self],
+    );
+
+    /// Decodes 1024 elements encoded by [`ALP::alp_pack`].
+    ///
+    /// # Panics
+    /// If the input is not exactly `header.packed_len()` words long.
+    fn unalp_pack_auto(
+        exponents: Exponents,
+        header: &FoRHeader<Self::UInt>,
+        patches: &Patches<Self>,
+        input: &[Self::UInt],
+        output: &mut [Self; 1024],
+    );
+
+    /// Returns the constant if the chunk packs into zero bits without any patches, i.e. every
+    /// element decodes from the reference.
+    fn alp_constant(
+        exponents: Exponents,
+        header: &FoRHeader<Self::UInt>,
+        patches: &Patches<Self>,
+    ) -> Option<Constant<Self>>;
+}
+
+macro_rules! impl_alp {
+    ($F:ty, $U:ty, $I:ty, $MAX_EXPONENT:expr, $MAGIC:expr, $F10:ident, $IF10:ident) => {
+        paste! {
+            impl ALP for $F {
+                type UInt = $U;
+
+                const MAX_EXPONENT: u8 = $MAX_EXPONENT;
+
+                #[allow(clippy::cast_sign_loss)]
+                fn find_exponents(input: &[Self; 1024]) -> Exponents {
+                    // Each exception costs a u16 index and a full value.
+                    const EXCEPTION_BITS: usize = 16 + 8 * size_of::<$F>();
+
+                    let mut best = Exponents { e: 0, f: 0 };
+                    let mut best_cost = usize::MAX;
+                    for e in 0..=Self::MAX_EXPONENT {
+                        for f in 0..=e {
+                            let mut exceptions = 0;
+                            let mut min = <$I>::MAX;
+                            let mut max = <$I>::MIN;
+                            for &value in input.iter().step_by(32) {
+                                let encoded = [<encode_ $F>](value, e, f);
+                                if [<decode_ $F>](encoded, e, f).to_bits() == value.to_bits() {
+                                    min = min.min(encoded);
+                                    max = max.max(encoded);
+                                } else {
+                                    exceptions += 1;
+                                }
+                            }
+
+                            let width = if min > max {
+                                0
+                            } else {
+                                (<$U>::T - (max.wrapping_sub(min) as $U).leading_zeros() as usize)
+                            };
+                            let cost = 32 * width + exceptions * EXCEPTION_BITS;
+                            if cost < best_cost {
+                                best = Exponents { e, f };
+                                best_cost = cost;
+                            }
+                        }
+                    }
+                    best
+                }
+
+                #[allow(clippy::cast_sign_loss)]
+                fn alp_encode(
+                    input: &[Self; 1024],
+                    exponents: Exponents,
+                    output: &mut [$U; 1024],
+                ) -> Patches<Self> {
+                    let Exponents { e, f } = exponents;
+                    let mut patches = Patches::new();
+                    let mut fill = None;
+                    for i in 0..1024 {
+                        let encoded = [<encode_ $F>](input[i], e, f);
+                        if [<decode_ $F>](encoded, e, f).to_bits() == input[i].to_bits() {
+                            output[i] = encoded as $U;
+                            fill.get_or_insert(encoded as $U);
+                        } else {
+                            patches.push(i, input[i]);
+                        }
+                    }
+
+                    let fill = fill.unwrap_or(0);
+                    for &index in &patches.indices {
+                        output[index as usize] = fill;
+                    }
+                    patches
+                }
+
+                #[allow(clippy::cast_sign_loss, clippy::cast_possible_wrap)]
+                fn alp_pack(
+                    input: &[Self; 1024],
+                    output: &mut [$U; 1024],
+                ) -> (Exponents, FoRHeader<$U>, Patches<Self>) {
+                    let exponents = Self::find_exponents(input);
+                    let mut encoded = [0; 1024];
+                    let patches = Self::alp_encode(input, exponents, &mut encoded);
+
+                    // The encoded integers are signed, so take the reference from the signed minimum.
+                    let min = encoded.iter().map(|&x| x as $I).min().unwrap_or(0);
+                    let max = encoded.iter().map(|&x| x as $I).max().unwrap_or(0);
+                    let header = FoRHeader {
+                        reference: min as $U,
+                        width: <$U>::T - (max.wrapping_sub(min) as $U).leading_zeros() as usize,
+                    };
+
+                    let packed_len = header.packed_len();
+                    unsafe {
+                        FoR::unchecked_for_pack(
+                            header.width,
+                            &encoded,
+                            header.reference,
+                            &mut output[..packed_len],
+                        );
+                    }
+                    (exponents, header, patches)
+                }
+
+                #[allow(clippy::cast_precision_loss, clippy::cast_possible_wrap)]
+                fn unalp_pack<const W: usize>(
+                    input: &[$U; 1024 * W / <$U>::T],
+                    exponents: Exponents,
+                    reference: $U,
+                    patches: &Patches<Self>,
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<$U>,
+                {
+                    let factor = $F10[exponents.f as usize];
+                    let inverse = $IF10[exponents.e as usize];
+                    for lane in 0..<$U>::LANES {
+                        unpack!($U, W, input, lane, |$idx, $elem| {
+                            let encoded = $elem.wrapping_add(reference) as $I;
+                            output[$idx] = encoded as $F * factor * inverse;
+                        });
+                    }
+                    patches.apply(output);
+                }
+
+                unsafe fn unchecked_unalp_pack(
+                    width: usize,
+                    input: &[$U],
+                    exponents: Exponents,
+                    reference: $U,
+                    patches: &Patches<Self>,
+                    output: &mut [Self],
+                ) {
+                    let packed_len = 128 * width / size_of::<$U>();
+                    debug_assert_eq!(input.len(), packed_len, "Input buffer must be of size 1024 * W / T");
+                    debug_assert_eq!(output.len(), 1024, "Output buffer must be of size 1024");
+                    debug_assert!(width <= <$U>::T, "Width must be less than or equal to {}", <$U>::T);
+
+                    seq_t!(W in $U {
+                        match width {
+                            #(W => Self::unalp_pack::<W>(
+                                array_ref![input, 0, 1024 * W / <$U>::T],
+                                exponents,
+                                reference,
+                                patches,
+                                array_mut_ref![output, 0, 1024],
+                            ),)*
+                            // seq_t has exclusive upper bound
+                            <$U>::T => Self::unalp_pack::<{ <$U>::T }>(
+                                array_ref![input, 0, 1024],
+                                exponents,
+                                reference,
+                                patches,
+                                array_mut_ref![output, 0, 1024],
+                            ),
+                            _ => unreachable!("Unsupported width: {}", width)
+                        }
+                    })
+                }
+
+                fn unalp_pack_auto(
+                    exponents: Exponents,
+                    header: &FoRHeader<$U>,
+                    patches: &Patches<Self>,
+                    input: &[$U],
+                    output: &mut [Self; 1024],
+                ) {
+                    assert_eq!(input.len(), header.packed_len(), "Input must be the packed length of the header");
+                    unsafe {
+                        Self::unchecked_unalp_pack(header.width, input, exponents, header.reference, patches, output);
+                    }
+                }
+
+                #[allow(clippy::cast_possible_wrap)]
+                fn alp_constant(
+                    exponents: Exponents,
+                    header: &FoRHeader<$U>,
+                    patches: &Patches<Self>,
+                ) -> Option<Constant<Self>> {
+                    (header.width == 0 && patches.is_empty()).then(|| {
+                        Constant::Value([<decode_ $F>](header.reference as $I, exponents.e, exponents.f))
+                    })
+                }
+            }
+
+            // Scales the value to an integer, rounding with the magic number `1.5 * 2^m` for an
+            // m-bit mantissa: adding it pushes the fraction out of the mantissa, and subtracting it
+            // leaves the rounded value.
+            #[allow(clippy::inline_always)]
+            #[inline(always)]
+            fn [<encode_ $F>](value: $F, e: u8, f: u8) -> $I {
+                let scaled = value * $F10[e as usize] * $IF10[f as usize];
+                ((scaled + $MAGIC) - $MAGIC) as $I
+            }
+
+            #[allow(clippy::inline_always, clippy::cast_precision_loss)]
+            #[inline(always)]
+            fn [<decode_ $F>](encoded: $I, e: u8, f: u8) -> $F {
+                encoded as $F * $F10[f as usize] * $IF10[e as usize]
+            }
+        }
+    };
+}
+
+static F10_F32: [f32; 11] = [1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10];
+static IF10_F32: [f32; 11] = [
+    1e0, 1e-1, 1e-2, 1e-3, 1e-4, 1e-5, 1e-6, 1e-7, 1e-8, 1e-9, 1e-10,
+];
+
+static F10_F64: [f64; 19] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18,
+];
+static IF10_F64: [f64; 19] = [
+    1e0, 1e-1, 1e-2, 1e-3, 1e-4, 1e-5, 1e-6, 1e-7, 1e-8, 1e-9, 1e-10, 1e-11, 1e-12, 1e-13, 1e-14,
+    1e-15, 1e-16, 1e-17, 1e-18,
+];
+
+// 1.5 * 2^23
+impl_alp!(f32, u32, i32, 10, 12_582_912.0, F10_F32, IF10_F32);
+// 1.5 * 2^52
+impl_alp!(
+    f64,
+    u64,
+    i64,
+    18,
+    6_755_399_441_055_744.0,
+    F10_F64,
+    IF10_F64
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_alp_f64_decimals() {
+        // Prices with two decimal places, plus values no exponent can encode.
+        let mut values: [f64; 1024] =
+            core::array::from_fn(|i| f64::from(1234 + (i as u32 * 37) % 5000) / 100.0);
+        values[3] = f64::NAN;
+        values[500] = -0.0;
+        values[1000] = 1e300;
+
+        let mut packed = [0; 1024];
+        let (exponents, header, patches) = ALP::alp_pack(&values, &mut packed);
+        assert_eq!(exponents.e - exponents.f, 2);
+        assert_eq!(header.width, 13);
+        assert_eq!(patches.indices, [3, 500, 1000]);
+
+        let mut decoded = [0.0; 1024];
+        ALP::unalp_pack_auto(
+            exponents,
+            &header,
+            &patches,
+            &packed[..header.packed_len()],
+            &mut decoded,
+        );
+        assert_eq!(decoded.map(f64::to_bits), values.map(f64::to_bits));
+    }
+
+    #[test]
+    fn test_alp_f32() {
+        let values: [f32; 1024] = core::array::from_fn(|i| (f32::from(i as u16) - 500.0) / 10.0);
+
+        let mut packed = [0; 1024];
+        let (exponents, header, patches) = ALP::alp_pack(&values, &mut packed);
+        assert_eq!(exponents.e - exponents.f, 1);
+        assert!(patches.is_empty());
+
+        let mut decoded = [0.0; 1024];
+        ALP::unalp_pack_auto(
+            exponents,
+            &header,
+            &patches,
+            &packed[..header.packed_len()],
+            &mut decoded,
+        );
+        assert_eq!(decoded.map(f32::to_bits), values.map(f32::to_bits));
+        assert_eq!(ALP::alp_constant(exponents, &header, &patches), None);
+    }
+
+    #[test]
+    fn test_alp_constant() {
+        let mut values = [-12.75f64; 1024];
+        let mut packed = [0; 1024];
+        let (exponents, header, patches) = ALP::alp_pack(&values, &mut packed);
+        assert_eq!(
+            ALP::alp_constant(exponents, &header, &patches),
+            Some(Constant::Value(-12.75))
+        );
+
+        // A patched outlier leaves the packed chunk constant, but not the decoded one.
+        values[9] = f64::NAN;
+        let (exponents, header, patches) = ALP::alp_pack(&values, &mut packed);
+        assert_eq!(header.width, 0);
+        assert_eq!(ALP::alp_constant(exponents, &header, &patches), None);
+    }
+}
@@ -0,0 +1,292 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use arrayref::{array_mut_ref, array_ref};
+use core::mem::size_of;
+use paste::paste;
+
+use crate::{
+    seq_t, unpack, BitPackWidth, BitPacking, Dict, Dictionary, FastLanes, Patches,
+    SupportedBitPackWidth,
+};
+
+/// The maximum number of left parts in the dictionary of an ALP-RD chunk.
+pub const MAX_LEFT_DICT: usize = 8;
+
+// The widest left part considered, so that it fits a `u16`.
+const MAX_LEFT_WIDTH: usize = 16;
+
+/// Where an ALP-RD chunk splits each value, and the dictionary of its most frequent left parts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Split {
+    /// The number of low bits in the right part.
+    pub right_width: usize,
+    pub left: Dict<u16>,
+}
+
+impl Split {
+    /// The number of `u16` words the left codes pack into.
+    #[must_use]
+    pub fn left_packed_len(&self) -> usize {
+        self.left.packed_len()
+    }
+
+    /// The number of `T` words the right parts pack into.
+    #[must_use]
+    pub fn right_packed_len<T: FastLanes>(&self) -> usize {
+        1024 * self.right_width / T::T
+    }
+}
+
+/// ALP for Real Doubles (ALP-RD), for floats with full mantissas that do not scale to integers.
+///
+/// Each value is split into a left part of its high bits and a right part of its low bits. The
+/// high bits vary little within a chunk, so the left parts are dictionary-encoded into a few bits,
+/// with left parts missing from the dictionary stored as [`Patches`]. The right parts are
+/// bit-packed as they are.
+pub trait ALPRD: Copy + Sized {
+    /// The unsigned integer holding the bits of a value.
+    type UInt: BitPacking;
+
+    /// Chooses the right width minimising the packed size plus the size of the exceptions,
+    /// estimated from a sample of the chunk.
+    fn find_split(input: &[Self; 1024]) -> usize;
+
+    /// ALP-RD-encodes 1024 elements, choosing the split and dictionary itself.
+    fn alp_rd_pack(
+        input: &[Self; 1024],
+        left: &mut [u16; 1024],
+        right: &mut [Self::UInt; 1024],
+    ) -> (Split, Patches<u16>);
+
+    /// Unpacks 1024 `W` bit right parts, joining each with its left part into a value.
+    fn unalp_rd_pack<const W: usize>(
+        right: &[Self::UInt; 1024 * W / <Self::UInt as FastLanes>::T],
+        left: &[u16; 1024],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self::UInt>;
+
+    /// Unpacks 1024 `W` bit right parts, joining each with its left part into a value, where `W`
+    /// is runtime-known instead of compile-time known.
+    ///
+    /// # Safety
+    /// The right slice must be of length `1024 * W / T`, where `T` is the bit-width of the
+    /// integers and `W` is the right width, which must be less than `T`. The output slice must be
+    /// of exactly length 1024.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_unalp_rd_pack(
+        width: usize,
+        right: &[Self::UInt],
+        left: &[u16; 1024],
+        output: &mut [Self],
+    );
+
+    /// Decodes 1024 elements encoded by [`ALPRD::alp_rd_pack`].
+    ///
+    /// # Panics
+    /// If the left or right input is not exactly the packed length given by the split.
+    fn unalp_rd_pack_auto(
+        split: &Split,
+        exceptions: &Patches<u16>,
+        left: &[u16],
+        right: &[Self::UInt],
+        output: &mut [Self; 1024],
+    );
+}
+
+// Returns the most frequent left parts, most frequent first, and the number of values whose left
+// part is not among them.
+fn top_left_parts(left_parts: impl Iterator<Item = u16>) -> (Vec<u16>, usize) {
+    let mut counts = BTreeMap::new();
+    let mut total = 0;
+    for left in left_parts {
+        *counts.entry(left).or_insert(0usize) += 1;
+        total += 1;
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by_key(|&(left, count)| (usize::MAX - count, left));
+    counts.truncate(MAX_LEFT_DICT);
+    let covered: usize = counts.iter().map(|&(_, count)| count).sum();
+    (
+        counts.into_iter().map(|(left, _)| left).collect(),
+        total - covered,
+    )
+}
+
+macro_rules! impl_alp_rd {
+    ($F:ty, $U:ty) => {
+        paste! {
+            impl ALPRD for $F {
+                type UInt = $U;
+
+                fn find_split(input: &[Self; 1024]) -> usize {
+                    // Each exception costs a u16 index and a u16 left part.
+                    const EXCEPTION_BITS: usize = 32;
+
+                    let mut best_width = <$U>::T - 1;
+                    let mut best_cost = usize::MAX;
+                    for left_width in 1..=MAX_LEFT_WIDTH {
+                        let right_width = <$U>::T - left_width;
+                        let lefts = input.iter().step_by(32).map(|v| (v.to_bits() >> right_width) as u16);
+                        let (dict, exceptions) = top_left_parts(lefts);
+
+                        let code_width = (usize::BITS - dict.len().saturating_sub(1).leading_zeros()) as usize;
+                        let cost = 32 * (right_width + code_width) + exceptions * EXCEPTION_BITS;
+                        if cost < best_cost {
+                            best_width = right_width;
+                            best_cost = cost;
+                        }
+                    }
+                    best_width
+                }
+
+                fn alp_rd_pack(
+                    input: &[Self; 1024],
+                    left: &mut [u16; 1024],
+                    right: &mut [$U; 1024],
+                ) -> (Split, Patches<u16>) {
+                    let right_width = Self::find_split(input);
+                    let bits = input.map(Self::to_bits);
+                    let lefts = bits.map(|b| (b >> right_width) as u16);
+
+                    let (dict, _) = top_left_parts(lefts.iter().copied());
+                    let split = Split {
+                        right_width,
+                        left: Dict::insertion_order(&dict),
+                    };
+
+                    // Left parts missing from the dictionary become exceptions, coded as the most
+                    // frequent left part in the meantime.
+                    let mut exceptions = Patches::new();
+                    let mut coded = lefts;
+                    for i in 0..1024 {
+                        if split.left.code(lefts[i]).is_none() {
+                            exceptions.push(i, lefts[i]);
+                            coded[i] = dict[0];
+                        }
+                    }
+                    let packed = Dictionary::dict_pack(&split.left, &coded, left);
+                    debug_assert!(packed.is_ok(), "every left part is coded");
+
+                    let mask: $U = (1 << right_width) - 1;
+                    let rights = bits.map(|b| b & mask);
+                    let right_len = split.right_packed_len::<$U>();
+                    unsafe { BitPacking::unchecked_pack(right_width, &rights, &mut right[..right_len]) };
+
+                    (split, exceptions)
+                }
+
+                fn unalp_rd_pack<const W: usize>(
+                    right: &[$U; 1024 * W / <$U>::T],
+                    left: &[u16; 1024],
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<$U>,
+                {
+                    for lane in 0..<$U>::LANES {
+                        unpack!($U, W, right, lane, |$idx, $elem| {
+                            output[$idx] = <$F>::from_bits(<$U>::from(left[$idx]) << W | $elem);
+                        });
+                    }
+                }
+
+                unsafe fn unchecked_unalp_rd_pack(
+                    width: usize,
+                    right: &[$U],
+                    left: &[u16; 1024],
+                    output: &mut [Self],
+                ) {
+                    let packed_len = 128 * width / size_of::<$U>();
+                    debug_assert_eq!(right.len(), packed_len, "Right buffer must be of size 1024 * W / T");
+                    debug_assert_eq!(output.len(), 1024, "Output buffer must be of size 1024");
+                    debug_assert!(width < <$U>::T, "Width must be less than {}", <$U>::T);
+
+                    seq_t!(W in $U {
+                        match width {
+                            #(W => Self::unalp_rd_pack::<W>(
+                                array_ref![right, 0, 1024 * W / <$U>::T],
+                                left,
+                                array_mut_ref![output, 0, 1024],
+                            ),)*
+                            _ => unreachable!("Unsupported width: {}", width)
+                        }
+                    })
+                }
+
+                fn unalp_rd_pack_auto(
+                    split: &Split,
+                    exceptions: &Patches<u16>,
+                    left: &[u16],
+                    right: &[$U],
+                    output: &mut [Self; 1024],
+                ) {
+                    assert_eq!(right.len(), split.right_packed_len::<$U>(), "Right input must be the packed length of the split");
+
+                    let mut lefts = [0; 1024];
+                    Dictionary::undict_pack_auto(&split.left, left, &mut lefts);
+                    exceptions.apply(&mut lefts);
+                    unsafe { Self::unchecked_unalp_rd_pack(split.right_width, right, &lefts, output) };
+                }
+            }
+        }
+    };
+}
+
+impl_alp_rd!(f32, u32);
+impl_alp_rd!(f64, u64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_alp_rd_f64() {
+        // Measurements with full mantissas, all of a similar magnitude.
+        let mut values: [f64; 1024] =
+            core::array::from_fn(|i| 1000.0 + (f64::from(i as u32) * 0.618_033_988_749_895).sin());
+        // More outliers of distinct magnitudes than the dictionary has room for.
+        let mut outlier = 1e-300;
+        for i in 0..12 {
+            values[100 + 50 * i] = outlier;
+            outlier *= 1e50;
+        }
+
+        let mut left = [0; 1024];
+        let mut right = [0; 1024];
+        let (split, exceptions) = ALPRD::alp_rd_pack(&values, &mut left, &mut right);
+        assert!(split.right_width < 64 - 8);
+        assert!(split.left.len() <= MAX_LEFT_DICT);
+        assert!(!exceptions.is_empty());
+
+        let mut decoded = [0.0; 1024];
+        ALPRD::unalp_rd_pack_auto(
+            &split,
+            &exceptions,
+            &left[..split.left_packed_len()],
+            &right[..split.right_packed_len::<u64>()],
+            &mut decoded,
+        );
+        assert_eq!(decoded.map(f64::to_bits), values.map(f64::to_bits));
+    }
+
+    #[test]
+    fn test_alp_rd_f32() {
+        let values: [f32; 1024] =
+            core::array::from_fn(|i| (f32::from(i as u16) * 0.37).cos() * 3.0);
+
+        let mut left = [0; 1024];
+        let mut right = [0; 1024];
+        let (split, exceptions) = ALPRD::alp_rd_pack(&values, &mut left, &mut right);
+
+        let mut decoded = [0.0; 1024];
+        ALPRD::unalp_rd_pack_auto(
+            &split,
+            &exceptions,
+            &left[..split.left_packed_len()],
+            &right[..split.right_packed_len::<u32>()],
+            &mut decoded,
+        );
+        assert_eq!(decoded.map(f32::to_bits), values.map(f32::to_bits));
+    }
+}
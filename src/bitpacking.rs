@@ -1,9 +1,13 @@
 use arrayref::{array_mut_ref, array_ref};
 use const_for::const_for;
+use core::marker::PhantomData;
 use core::mem::size_of;
 use paste::paste;
 
-use crate::{pack, seq_t, unpack, FastLanes, Pred, Satisfied, FL_ORDER};
+use crate::{
+    ordered_lane_of, ordered_row_of, pack, seq_t, transpose, unpack, FastLanes, FastLanesOrder,
+    LaneOrder, Pred, Satisfied,
+};
 
 pub struct BitPackWidth<const W: usize>;
 pub trait SupportedBitPackWidth<T> {}
@@ -13,6 +17,14 @@ impl<const W: usize, T> SupportedBitPackWidth<T> for BitPackWidth<W> where
 }
 
 /// `BitPack` into a compile-time known bit-width.
+///
+/// The encodings built on top that choose their width per chunk, such as [`FoR::for_pack_auto`],
+/// write the packed words to the front of a 1024-element output and return a header from which
+/// their count and everything else needed to decode them follows. Their `_auto` decoders take
+/// the header and dispatch on its width through an `unchecked_*` method like
+/// [`BitPacking::unchecked_unpack`].
+///
+/// [`FoR::for_pack_auto`]: crate::FoR::for_pack_auto
 pub trait BitPacking: FastLanes {
     /// Packs 1024 elements into W bits each.
     /// The output is given as Self to ensure correct alignment.
@@ -20,6 +32,13 @@ pub trait BitPacking: FastLanes {
     where
         BitPackWidth<W>: SupportedBitPackWidth<Self>;
 
+    /// Packs 1024 elements into `W` bits each, laid out in the lane order `O`.
+    fn pack_ordered<const W: usize, O: LaneOrder>(
+        input: &[Self; 1024],
+        output: &mut [Self; 1024 * W / Self::T],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
     /// Packs 1024 elements into `W` bits each, where `W` is runtime-known instead of
     /// compile-time known.
     ///
@@ -34,6 +53,13 @@ pub trait BitPacking: FastLanes {
     where
         BitPackWidth<W>: SupportedBitPackWidth<Self>;
 
+    /// Unpacks 1024 elements from `W` bits each, laid out in the lane order `O`.
+    fn unpack_ordered<const W: usize, O: LaneOrder>(
+        input: &[Self; 1024 * W / Self::T],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
     /// Unpacks 1024 elements from `W` bits each, where `W` is runtime-known instead of
     /// compile-time known.
     ///
@@ -43,11 +69,39 @@ pub trait BitPacking: FastLanes {
     /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
     unsafe fn unchecked_unpack(width: usize, input: &[Self], output: &mut [Self]);
 
+    /// Unpacks 1024 transposed elements from `W` bits each, writing each straight to its
+    /// untransposed position.
+    ///
+    /// Equivalent to `BitPacking::unpack` followed by `Transpose::untranspose`.
+    fn unpack_untranspose<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Unpacks 1024 transposed elements from `W` bits each, writing each straight to its
+    /// untransposed position, where `W` is runtime-known instead of compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of length `1024 * W / T`, where `T` is the bit-width of Self and `W`
+    /// is the packed width. The output slice must be of exactly length 1024.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_unpack_untranspose(width: usize, input: &[Self], output: &mut [Self]);
+
     /// Unpacks a single element at the provided index from a packed array of 1024 `W` bit elements.
     fn unpack_single<const W: usize>(packed: &[Self; 1024 * W / Self::T], index: usize) -> Self
     where
         BitPackWidth<W>: SupportedBitPackWidth<Self>;
 
+    /// Unpacks a single element at the provided index from a packed array of 1024 `W` bit elements
+    /// packed in the lane order `O`, such as by `pack!(T, O, W, ...)`.
+    fn unpack_single_ordered<const W: usize, O: LaneOrder>(
+        packed: &[Self; 1024 * W / Self::T],
+        index: usize,
+    ) -> Self
+    where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
     /// Unpacks a single element at the provided index from a packed array of 1024 `W` bit elements,
     /// where `W` is runtime-known instead of compile-time known.
     ///
@@ -65,9 +119,16 @@ macro_rules! impl_packing {
                 fn pack<const W: usize>(
                     input: &[Self; 1024],
                     output: &mut [Self; 1024 * W / Self::T],
+                ) where BitPackWidth<W>: SupportedBitPackWidth<Self> {
+                    Self::pack_ordered::<W, FastLanesOrder>(input, output);
+                }
+
+                fn pack_ordered<const W: usize, O: LaneOrder>(
+                    input: &[Self; 1024],
+                    output: &mut [Self; 1024 * W / Self::T],
                 ) where BitPackWidth<W>: SupportedBitPackWidth<Self> {
                     for lane in 0..Self::LANES {
-                        pack!($T, W, output, lane, |$idx| {
+                        pack!($T, O, W, output, lane, |$idx| {
                             input[$idx]
                         });
                     }
@@ -98,9 +159,16 @@ macro_rules! impl_packing {
                 fn unpack<const W: usize>(
                     input: &[Self; 1024 * W / Self::T],
                     output: &mut [Self; 1024],
+                ) where BitPackWidth<W>: SupportedBitPackWidth<Self> {
+                    Self::unpack_ordered::<W, FastLanesOrder>(input, output);
+                }
+
+                fn unpack_ordered<const W: usize, O: LaneOrder>(
+                    input: &[Self; 1024 * W / Self::T],
+                    output: &mut [Self; 1024],
                 ) where BitPackWidth<W>: SupportedBitPackWidth<Self> {
                     for lane in 0..Self::LANES {
-                        unpack!($T, W, input, lane, |$idx, $elem| {
+                        unpack!($T, O, W, input, lane, |$idx, $elem| {
                             output[$idx] = $elem
                         });
                     }
@@ -128,8 +196,51 @@ macro_rules! impl_packing {
                     })
                 }
 
+                fn unpack_untranspose<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    output: &mut [Self; 1024],
+                ) where BitPackWidth<W>: SupportedBitPackWidth<Self> {
+                    for lane in 0..Self::LANES {
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            output[transpose($idx)] = $elem
+                        });
+                    }
+                }
+
+                unsafe fn unchecked_unpack_untranspose(width: usize, input: &[Self], output: &mut [Self]) {
+                    let packed_len = 128 * width / size_of::<Self>();
+                    debug_assert_eq!(input.len(), packed_len, "Input buffer must be of size 1024 * W / T");
+                    debug_assert_eq!(output.len(), 1024, "Output buffer must be of size 1024");
+                    debug_assert!(width <= Self::T, "Width must be less than or equal to {}", Self::T);
+
+                    seq_t!(W in $T {
+                        match width {
+                            #(W => Self::unpack_untranspose::<W>(
+                                array_ref![input, 0, 1024 * W / <$T>::T],
+                                array_mut_ref![output, 0, 1024],
+                            ),)*
+                            // seq_t has exclusive upper bound
+                            Self::T => Self::unpack_untranspose::<{ Self::T }>(
+                                array_ref![input, 0, 1024],
+                                array_mut_ref![output, 0, 1024],
+                            ),
+                            _ => unreachable!("Unsupported width: {}", width)
+                        }
+                    })
+                }
+
                 /// Unpacks a single element at the provided index from a packed array of 1024 `W` bit elements.
                 fn unpack_single<const W: usize>(packed: &[Self; 1024 * W / Self::T], index: usize) -> Self
+                where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    Self::unpack_single_ordered::<W, FastLanesOrder>(packed, index)
+                }
+
+                fn unpack_single_ordered<const W: usize, O: LaneOrder>(
+                    packed: &[Self; 1024 * W / Self::T],
+                    index: usize,
+                ) -> Self
                 where
                     BitPackWidth<W>: SupportedBitPackWidth<Self>,
                 {
@@ -150,11 +261,10 @@ macro_rules! impl_packing {
                     //
                     // First step, we need to get the lane and row for interpretation #1 above.
                     assert!(index < 1024, "Index must be less than 1024, got {}", index);
-                    let (lane, row): (usize, usize) = {
-                        const LANES: [u8; 1024] = lanes_by_index::<$T>();
-                        const ROWS: [u8; 1024] = rows_by_index::<$T>();
-                        (LANES[index] as usize, ROWS[index] as usize)
-                    };
+                    let (lane, row): (usize, usize) = (
+                        IndexTables::<$T, O>::LANES[index] as usize,
+                        IndexTables::<$T, O>::ROWS[index] as usize,
+                    );
 
                     if W == <$T>::T {
                         // Special case for W==T, we can just read the value directly
@@ -203,30 +313,29 @@ macro_rules! impl_packing {
     };
 }
 
+// The lane and row of each index in the lane order `O`, computed at compile-time to speed up
+// unpack_single at runtime.
+pub(crate) struct IndexTables<T, O>(PhantomData<(T, O)>);
+
+impl<T: FastLanes, O: LaneOrder> IndexTables<T, O> {
+    pub(crate) const LANES: [u8; 1024] = lanes_by_index::<T, O>();
+    pub(crate) const ROWS: [u8; 1024] = rows_by_index::<T, O>();
+}
+
 // helper function executed at compile-time to speed up unpack_single at runtime
-const fn lanes_by_index<T: FastLanes>() -> [u8; 1024] {
+const fn lanes_by_index<T: FastLanes, O: LaneOrder>() -> [u8; 1024] {
     let mut lanes = [0u8; 1024];
     const_for!(i in 0..1024 => {
-        lanes[i] = (i % T::LANES) as u8;
+        lanes[i] = ordered_lane_of::<T, O>(i) as u8;
     });
     lanes
 }
 
 // helper function executed at compile-time to speed up unpack_single at runtime
-const fn rows_by_index<T: FastLanes>() -> [u8; 1024] {
+const fn rows_by_index<T: FastLanes, O: LaneOrder>() -> [u8; 1024] {
     let mut rows = [0u8; 1024];
     const_for!(i in 0..1024 => {
-        // This is the inverse of the `index` function from the pack/unpack macros:
-        //     fn index(row: usize, lane: usize) -> usize {
-        //         let o = row / 8;
-        //         let s = row % 8;
-        //         (FL_ORDER[o] * 16) + (s * 128) + lane
-        //     }
-        let lane = i % T::LANES;
-        let s = i / 128; // because `(FL_ORDER[o] * 16) + lane` is always < 128
-        let fl_order = (i - s * 128 - lane) / 16; // value of FL_ORDER[o]
-        let o = FL_ORDER[fl_order]; // because this transposition is invertible!
-        rows[i] = (o * 8 + s) as u8;
+        rows[i] = ordered_row_of::<T, O>(i) as u8;
     });
     rows
 }
@@ -244,6 +353,8 @@ mod test {
     use seq_macro::seq;
 
     use super::*;
+    use crate::test::SpreadOrder;
+    use crate::{IdentityOrder, Transpose};
 
     #[test]
     fn test_unchecked_pack() {
@@ -270,6 +381,47 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_pack_ordered() {
+        fn check<O: LaneOrder>() {
+            let values: [u32; 1024] = array::from_fn(|i| (i * 7 % 2048) as u32);
+            let mut packed = [0; 352];
+            BitPacking::pack_ordered::<11, O>(&values, &mut packed);
+
+            let mut output = [0; 1024];
+            BitPacking::unpack_ordered::<11, O>(&packed, &mut output);
+            assert_eq!(values, output);
+            for i in 0..1024 {
+                assert_eq!(
+                    BitPacking::unpack_single_ordered::<11, O>(&packed, i),
+                    values[i]
+                );
+            }
+        }
+
+        check::<FastLanesOrder>();
+        check::<IdentityOrder>();
+        check::<SpreadOrder>();
+    }
+
+    #[test]
+    fn test_unpack_untranspose() {
+        let values: [u16; 1024] = array::from_fn(|i| (i % 2048) as u16);
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+
+        let mut packed = [0; 704];
+        BitPacking::pack::<11>(&transposed, &mut packed);
+
+        let mut output = [0; 1024];
+        BitPacking::unpack_untranspose::<11>(&packed, &mut output);
+        assert_eq!(values, output);
+
+        let mut output = [0; 1024];
+        unsafe { BitPacking::unchecked_unpack_untranspose(11, &packed, &mut output) };
+        assert_eq!(values, output);
+    }
+
     fn try_round_trip<T: BitPacking + Debug, const W: usize>()
     where
         BitPackWidth<W>: SupportedBitPackWidth<T>,
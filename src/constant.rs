@@ -0,0 +1,175 @@
+use crate::{BitEq, FastLanes};
+
+/// A chunk whose 1024 elements are all equal or all null, stored without any payload.
+///
+/// Decoding fills the output, and predicates and aggregates are answered from the value alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Constant<T> {
+    /// Every element is null.
+    Null,
+    /// Every element is valid and equal to the value.
+    Value(T),
+}
+
+impl<T: BitEq> Constant<T> {
+    /// Returns the constant if all elements of the chunk have the same bits.
+    #[must_use]
+    pub fn detect(input: &[T; 1024]) -> Option<Self> {
+        let value = input[0];
+        input
+            .iter()
+            .all(|&v| v.bit_eq(value))
+            .then_some(Self::Value(value))
+    }
+
+    /// Returns the constant if all elements of the chunk are null, or all are valid and equal.
+    #[must_use]
+    pub fn detect_nullable(input: &[T; 1024], validity: &[u64; 16]) -> Option<Self> {
+        if validity.iter().all(|&word| word == 0) {
+            Some(Self::Null)
+        } else if validity.iter().all(|&word| word == u64::MAX) {
+            Self::detect(input)
+        } else {
+            None
+        }
+    }
+
+    /// The validity bitmap of the chunk, with bit `i % 64` of word `i / 64` set if element `i` is
+    /// valid.
+    #[must_use]
+    pub fn validity(&self) -> [u64; 16] {
+        match self {
+            Self::Null => [0; 16],
+            Self::Value(_) => [u64::MAX; 16],
+        }
+    }
+
+    /// Fills the output with the value, or with `null` if every element is null.
+    pub fn fill(&self, null: T, output: &mut [T; 1024]) {
+        match *self {
+            Self::Null => output.fill(null),
+            Self::Value(value) => output.fill(value),
+        }
+    }
+
+    /// Evaluates the predicate once, returning the bitmap of elements that satisfy it. Null
+    /// elements never do.
+    pub fn filter(&self, predicate: impl FnOnce(T) -> bool) -> [u64; 16] {
+        match *self {
+            Self::Value(value) if predicate(value) => [u64::MAX; 16],
+            _ => [0; 16],
+        }
+    }
+
+    /// Evaluates the predicate once, returning the number of elements that satisfy it.
+    pub fn count(&self, predicate: impl FnOnce(T) -> bool) -> usize {
+        match *self {
+            Self::Value(value) if predicate(value) => 1024,
+            _ => 0,
+        }
+    }
+
+    /// The smallest and largest element, or `None` if every element is null.
+    #[must_use]
+    pub fn min_max(&self) -> Option<(T, T)> {
+        match *self {
+            Self::Null => None,
+            Self::Value(value) => Some((value, value)),
+        }
+    }
+
+    /// The sum of the elements, or `None` if every element is null.
+    #[must_use]
+    pub fn sum(&self) -> Option<u128>
+    where
+        T: FastLanes,
+    {
+        match *self {
+            Self::Null => None,
+            Self::Value(value) => value.to_u128().map(|v| v * 1024),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Dict, FoR, Linear, RunLength};
+
+    #[test]
+    fn test_detect() {
+        let values = [42u32; 1024];
+        let constant = Constant::detect(&values).expect("constant chunk");
+        assert_eq!(constant, Constant::Value(42));
+        assert_eq!(constant.sum(), Some(42 * 1024));
+        assert_eq!(constant.min_max(), Some((42, 42)));
+        assert_eq!(constant.count(|v| v > 40), 1024);
+        assert_eq!(constant.filter(|v| v < 40), [0; 16]);
+
+        let mut decoded = [0; 1024];
+        constant.fill(0, &mut decoded);
+        assert_eq!(decoded, values);
+
+        let mut varied = values;
+        varied[1023] = 43;
+        assert_eq!(Constant::detect(&varied), None);
+    }
+
+    #[test]
+    fn test_detect_bits() {
+        // Signed zeros compare equal, but are not the same constant.
+        let mut zeros = [0.0f64; 1024];
+        zeros[512] = -0.0;
+        assert_eq!(Constant::detect(&zeros), None);
+
+        // A NaN never compares equal, but a chunk of the same NaN is constant.
+        let constant = Constant::detect(&[f32::NAN; 1024]).expect("constant chunk");
+        assert!(matches!(constant, Constant::Value(v) if v.to_bits() == f32::NAN.to_bits()));
+    }
+
+    #[test]
+    fn test_detect_nullable() {
+        let values: [u16; 1024] = core::array::from_fn(|i| i as u16);
+        let constant = Constant::detect_nullable(&values, &[0; 16]).expect("all null");
+        assert_eq!(constant, Constant::Null);
+        assert_eq!(constant.sum(), None);
+        assert_eq!(constant.count(|_| true), 0);
+        assert_eq!(constant.validity(), [0; 16]);
+
+        let mut validity = [u64::MAX; 16];
+        assert_eq!(
+            Constant::detect_nullable(&[7u16; 1024], &validity),
+            Some(Constant::Value(7))
+        );
+        validity[3] = 1;
+        assert_eq!(Constant::detect_nullable(&[7u16; 1024], &validity), None);
+    }
+
+    #[test]
+    fn test_encoders_detect_constant() {
+        let values = [u64::MAX; 1024];
+        let expected = Some(Constant::Value(u64::MAX));
+
+        let mut packed = [0; 1024];
+        let header = FoR::for_pack_auto(&values, &mut packed);
+        assert_eq!(header.constant(), expected);
+        assert_eq!(Dict::sorted(&values).constant(), expected);
+
+        let mut runs = [0; 1024];
+        assert_eq!(RunLength::rle_pack(&values, &mut runs).constant(), expected);
+        assert_eq!(
+            Linear::linear_pack(&values, &mut packed).constant(),
+            expected
+        );
+
+        let varied: [u64; 1024] = core::array::from_fn(|i| i as u64 % 2);
+        assert_eq!(FoR::for_pack_auto(&varied, &mut packed).constant(), None);
+        assert_eq!(Dict::sorted(&varied).constant(), None);
+
+        // An exact line is only constant when it is flat.
+        let sequence: [u64; 1024] = core::array::from_fn(|i| i as u64);
+        let line = Linear::linear_pack(&sequence, &mut packed);
+        assert!(line.is_exact());
+        assert_eq!(line.constant(), None);
+    }
+}
@@ -1,19 +1,142 @@
 #![allow(unused_assignments)]
 
-use crate::{iterate, unpack, BitPackWidth, BitPacking, FastLanes, SupportedBitPackWidth};
+use crate::{
+    index_of, iterate, pack, transpose, unpack, BitPackWidth, BitPacking, FastLanes,
+    FastLanesOrder, IndexTables, LaneOrder, SupportedBitPackWidth,
+};
 use paste::paste;
 
 pub trait Delta: BitPacking {
     fn delta(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]);
 
+    /// Delta-encodes each lane's rows from its base, with the rows laid out in the lane order `O`.
+    ///
+    /// Only under the default `FastLanesOrder` do the lanes of transposed input hold consecutive
+    /// values.
+    fn delta_ordered<O: LaneOrder>(
+        input: &[Self; 1024],
+        base: &[Self; Self::LANES],
+        output: &mut [Self; 1024],
+    );
+
     fn undelta(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]);
 
+    /// Decodes each lane's rows from its base, with the rows laid out in the lane order `O`.
+    fn undelta_ordered<O: LaneOrder>(
+        input: &[Self; 1024],
+        base: &[Self; Self::LANES],
+        output: &mut [Self; 1024],
+    );
+
     fn undelta_pack<const W: usize>(
         input: &[Self; 1024 * W / Self::T],
         base: &[Self; Self::LANES],
         output: &mut [Self; 1024],
     ) where
         BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Unpacks and decodes deltas packed into `W` bits each, with the rows laid out in the lane
+    /// order `O`, such as by `BitPacking::pack_ordered`.
+    fn undelta_pack_ordered<const W: usize, O: LaneOrder>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Delta-encodes the input, zigzag-encoding each delta so that small decreases also produce
+    /// small unsigned values.
+    fn zigzag_delta(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]);
+
+    fn unzigzag_delta(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]);
+
+    fn unzigzag_delta_pack<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Delta-encodes the input and packs each delta less `reference` into `W` bits.
+    ///
+    /// For strictly increasing input, choosing the minimum delta as the reference removes the
+    /// step every delta shares.
+    fn delta_for_pack<const W: usize>(
+        input: &[Self; 1024],
+        base: &[Self; Self::LANES],
+        reference: Self,
+        output: &mut [Self; 1024 * W / Self::T],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    fn undelta_for_pack<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        reference: Self,
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Transposes linear-order input, delta-encodes it and packs the deltas into `W` bits,
+    /// all in a single pass.
+    ///
+    /// Equivalent to `Transpose::transpose`, then `Delta::delta`, then `BitPacking::pack`.
+    fn transpose_delta_pack<const W: usize>(
+        input: &[Self; 1024],
+        base: &[Self; Self::LANES],
+        output: &mut [Self; 1024 * W / Self::T],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Unpacks and decodes the deltas, writing each value straight to its linear-order position.
+    ///
+    /// Equivalent to `Delta::undelta_pack` followed by `Transpose::untranspose`.
+    fn undelta_pack_untranspose<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Decodes the single element at the provided index of a packed delta chunk.
+    ///
+    /// Only the lane holding the index is decoded, and only up to the row of the index.
+    fn undelta_single<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        index: usize,
+    ) -> Self
+    where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Decodes the elements at each of the provided indices of a packed delta chunk.
+    fn undelta_gather<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        indices: &[usize],
+        output: &mut [Self],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Records the value preceding every group of 8 rows in each lane, such that
+    /// `output[group * LANES + lane]` is the base to decode that group from.
+    ///
+    /// The first group's checkpoints are the bases themselves.
+    fn delta_checkpoints(
+        input: &[Self; 1024],
+        base: &[Self; Self::LANES],
+        output: &mut [Self; 128],
+    );
+
+    /// Decodes the single element at the provided index of a packed delta chunk, starting from
+    /// the nearest checkpoint so that at most 8 deltas are summed.
+    fn undelta_single_checkpointed<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        checkpoints: &[Self; 128],
+        index: usize,
+    ) -> Self
+    where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
 }
 
 macro_rules! impl_delta {
@@ -22,9 +145,17 @@ macro_rules! impl_delta {
             impl Delta for $T {
                 #[inline(never)]
                 fn delta(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]) {
+                    Self::delta_ordered::<FastLanesOrder>(input, base, output);
+                }
+
+                fn delta_ordered<O: LaneOrder>(
+                    input: &[Self; 1024],
+                    base: &[Self; Self::LANES],
+                    output: &mut [Self; 1024],
+                ) {
                     for lane in 0..Self::LANES {
                         let mut prev = base[lane];
-                        iterate!($T, lane, |$idx| {
+                        iterate!($T, O, lane, |$idx| {
                             let next = input[$idx];
                             output[$idx] = next.wrapping_sub(prev);
                             prev = next;
@@ -34,9 +165,17 @@ macro_rules! impl_delta {
 
                 #[inline(never)]
                 fn undelta(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]) {
+                    Self::undelta_ordered::<FastLanesOrder>(input, base, output);
+                }
+
+                fn undelta_ordered<O: LaneOrder>(
+                    input: &[Self; 1024],
+                    base: &[Self; Self::LANES],
+                    output: &mut [Self; 1024],
+                ) {
                     for lane in 0..Self::LANES {
                         let mut prev = base[lane];
-                        iterate!($T, lane, |$idx| {
+                        iterate!($T, O, lane, |$idx| {
                             let next = input[$idx].wrapping_add(prev);
                             output[$idx] = next;
                             prev = next;
@@ -51,16 +190,225 @@ macro_rules! impl_delta {
                     output: &mut [Self; 1024],
                 ) where
                     BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    Self::undelta_pack_ordered::<W, FastLanesOrder>(input, base, output);
+                }
+
+                fn undelta_pack_ordered<const W: usize, O: LaneOrder>(
+                    input: &[Self; 1024 * W / Self::T],
+                    base: &[Self; Self::LANES],
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
                 {
                     for lane in 0..Self::LANES {
                         let mut prev = base[lane];
-                        unpack!($T, W, input, lane, |$idx, $elem| {
+                        unpack!($T, O, W, input, lane, |$idx, $elem| {
                             let next = $elem.wrapping_add(prev);
                             output[$idx] = next;
                             prev = next;
                         });
                     }
                 }
+
+                #[inline(never)]
+                fn zigzag_delta(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]) {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        iterate!($T, lane, |$idx| {
+                            let next = input[$idx];
+                            let delta = next.wrapping_sub(prev);
+                            // Move the sign bit into the least significant bit.
+                            output[$idx] = (delta << 1) ^ (delta >> (Self::T - 1)).wrapping_neg();
+                            prev = next;
+                        });
+                    }
+                }
+
+                #[inline(never)]
+                fn unzigzag_delta(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]) {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        iterate!($T, lane, |$idx| {
+                            let zigzag = input[$idx];
+                            let delta = (zigzag >> 1) ^ (zigzag & 1).wrapping_neg();
+                            let next = delta.wrapping_add(prev);
+                            output[$idx] = next;
+                            prev = next;
+                        });
+                    }
+                }
+
+                #[inline(never)]
+                fn unzigzag_delta_pack<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    base: &[Self; Self::LANES],
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            let delta = ($elem >> 1) ^ ($elem & 1).wrapping_neg();
+                            let next = delta.wrapping_add(prev);
+                            output[$idx] = next;
+                            prev = next;
+                        });
+                    }
+                }
+
+                #[inline(never)]
+                fn delta_for_pack<const W: usize>(
+                    input: &[Self; 1024],
+                    base: &[Self; Self::LANES],
+                    reference: Self,
+                    output: &mut [Self; 1024 * W / Self::T],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        pack!($T, W, output, lane, |$idx| {
+                            let next = input[$idx];
+                            let delta = next.wrapping_sub(prev);
+                            prev = next;
+                            delta.wrapping_sub(reference)
+                        });
+                    }
+                }
+
+                #[inline(never)]
+                fn undelta_for_pack<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    base: &[Self; Self::LANES],
+                    reference: Self,
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            let next = $elem.wrapping_add(reference).wrapping_add(prev);
+                            output[$idx] = next;
+                            prev = next;
+                        });
+                    }
+                }
+
+                #[inline(never)]
+                fn transpose_delta_pack<const W: usize>(
+                    input: &[Self; 1024],
+                    base: &[Self; Self::LANES],
+                    output: &mut [Self; 1024 * W / Self::T],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        pack!($T, W, output, lane, |$idx| {
+                            let next = input[transpose($idx)];
+                            let delta = next.wrapping_sub(prev);
+                            prev = next;
+                            delta
+                        });
+                    }
+                }
+
+                #[inline(never)]
+                fn undelta_pack_untranspose<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    base: &[Self; Self::LANES],
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            let next = $elem.wrapping_add(prev);
+                            output[transpose($idx)] = next;
+                            prev = next;
+                        });
+                    }
+                }
+
+                fn undelta_single<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    base: &[Self; Self::LANES],
+                    index: usize,
+                ) -> Self
+                where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    assert!(index < 1024, "Index must be less than 1024, got {}", index);
+                    let (lane, row): (usize, usize) = (
+                        IndexTables::<$T, FastLanesOrder>::LANES[index] as usize,
+                        IndexTables::<$T, FastLanesOrder>::ROWS[index] as usize,
+                    );
+
+                    let mut value = base[lane];
+                    for r in 0..=row {
+                        let idx = index_of::<$T>(r, lane);
+                        value = value.wrapping_add(Self::unpack_single::<W>(input, idx));
+                    }
+                    value
+                }
+
+                fn undelta_gather<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    base: &[Self; Self::LANES],
+                    indices: &[usize],
+                    output: &mut [Self],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    assert_eq!(indices.len(), output.len(), "Indices and output lengths must match");
+                    for (&index, out) in indices.iter().zip(output.iter_mut()) {
+                        *out = Self::undelta_single::<W>(input, base, index);
+                    }
+                }
+
+                fn delta_checkpoints(
+                    input: &[Self; 1024],
+                    base: &[Self; Self::LANES],
+                    output: &mut [Self; 128],
+                ) {
+                    for group in 0..Self::T / 8 {
+                        for lane in 0..Self::LANES {
+                            output[group * Self::LANES + lane] = if group == 0 {
+                                base[lane]
+                            } else {
+                                // The last row of the previous group.
+                                input[index_of::<$T>(group * 8 - 1, lane)]
+                            };
+                        }
+                    }
+                }
+
+                fn undelta_single_checkpointed<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    checkpoints: &[Self; 128],
+                    index: usize,
+                ) -> Self
+                where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    assert!(index < 1024, "Index must be less than 1024, got {}", index);
+                    let (lane, row): (usize, usize) = (
+                        IndexTables::<$T, FastLanesOrder>::LANES[index] as usize,
+                        IndexTables::<$T, FastLanesOrder>::ROWS[index] as usize,
+                    );
+
+                    let group = row / 8;
+                    let mut value = checkpoints[group * Self::LANES + lane];
+                    for r in group * 8..=row {
+                        let idx = index_of::<$T>(r, lane);
+                        value = value.wrapping_add(Self::unpack_single::<W>(input, idx));
+                    }
+                    value
+                }
             }
         }
     };
@@ -74,7 +422,8 @@ impl_delta!(u64);
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::Transpose;
+    use crate::test::SpreadOrder;
+    use crate::{FoR, IdentityOrder, Transpose};
     use core::mem::size_of;
 
     #[test]
@@ -105,4 +454,145 @@ mod test {
         Delta::undelta(&unpacked, &[0; 64], &mut undelta);
         assert_eq!(transposed, undelta);
     }
+
+    #[test]
+    fn test_delta_ordered() {
+        fn check<O: LaneOrder>() {
+            let values: [u16; 1024] = core::array::from_fn(|i| (i * 3) as u16);
+            let base = [0; 64];
+
+            let mut deltas = [0; 1024];
+            Delta::delta_ordered::<O>(&values, &base, &mut deltas);
+            let mut packed = [0; 1024];
+            BitPacking::pack_ordered::<16, O>(&deltas, &mut packed);
+
+            let mut unpacked = [0; 1024];
+            Delta::undelta_pack_ordered::<16, O>(&packed, &base, &mut unpacked);
+            assert_eq!(values, unpacked);
+
+            let mut undelta = [0; 1024];
+            Delta::undelta_ordered::<O>(&deltas, &base, &mut undelta);
+            assert_eq!(values, undelta);
+        }
+
+        check::<FastLanesOrder>();
+        check::<IdentityOrder>();
+        check::<SpreadOrder>();
+    }
+
+    #[test]
+    fn test_delta_for() {
+        const W: usize = 3;
+        // Strictly increasing by at least 1000.
+        let values: [u32; 1024] = core::array::from_fn(|i| (i * 1000 + (i * i) % 7) as u32);
+
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+
+        let base: [u32; 32] = core::array::from_fn(|lane| transposed[lane].wrapping_sub(1000));
+        let mut deltas = [0; 1024];
+        Delta::delta(&transposed, &base, &mut deltas);
+        let reference = *deltas.iter().min().unwrap();
+        assert!(deltas.iter().all(|&d| d - reference < (1 << W)));
+
+        let mut packed = [0; 128 * W / size_of::<u32>()];
+        Delta::delta_for_pack::<W>(&transposed, &base, reference, &mut packed);
+
+        // Fused kernel
+        let mut unpacked = [0; 1024];
+        Delta::undelta_for_pack::<W>(&packed, &base, reference, &mut unpacked);
+        assert_eq!(transposed, unpacked);
+
+        // Unfused kernels
+        let mut unfor = [0; 1024];
+        FoR::unfor_pack::<W>(&packed, reference, &mut unfor);
+        assert_eq!(deltas, unfor);
+    }
+
+    #[test]
+    fn test_transpose_delta() {
+        const W: usize = 5;
+        let values: [u16; 1024] = core::array::from_fn(|i| (i * 7 + i % 3) as u16);
+        // Row 0 of each lane is the transposed index of the lane itself.
+        let base: [u16; 64] = core::array::from_fn(|lane| values[transpose(lane)].wrapping_sub(1));
+
+        let mut packed = [0; 128 * W / size_of::<u16>()];
+        Delta::transpose_delta_pack::<W>(&values, &base, &mut packed);
+
+        // Same output as the three separate passes.
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+        let mut deltas = [0; 1024];
+        Delta::delta(&transposed, &base, &mut deltas);
+        let mut unfused = [0; 128 * W / size_of::<u16>()];
+        BitPacking::pack::<W>(&deltas, &mut unfused);
+        assert_eq!(packed, unfused);
+
+        let mut unpacked = [0; 1024];
+        Delta::undelta_pack_untranspose::<W>(&packed, &base, &mut unpacked);
+        assert_eq!(values, unpacked);
+    }
+
+    #[test]
+    fn test_undelta_single() {
+        const W: usize = 6;
+        let values: [u32; 1024] = core::array::from_fn(|i| (i * 37 + (i * i) % 13) as u32);
+
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+
+        let base: [u32; 32] = core::array::from_fn(|lane| transposed[lane].wrapping_sub(5));
+        let mut deltas = [0; 1024];
+        Delta::delta(&transposed, &base, &mut deltas);
+
+        let mut packed = [0; 128 * W / size_of::<u32>()];
+        BitPacking::pack::<W>(&deltas, &mut packed);
+
+        let mut checkpoints = [0; 128];
+        Delta::delta_checkpoints(&transposed, &base, &mut checkpoints);
+
+        for i in 0..1024 {
+            assert_eq!(Delta::undelta_single::<W>(&packed, &base, i), transposed[i]);
+            assert_eq!(
+                Delta::undelta_single_checkpointed::<W>(&packed, &checkpoints, i),
+                transposed[i]
+            );
+        }
+
+        let indices = [1023, 0, 512, 17, 17];
+        let mut gathered = [0; 5];
+        Delta::undelta_gather::<W>(&packed, &base, &indices, &mut gathered);
+        assert_eq!(gathered, indices.map(|i| transposed[i]));
+    }
+
+    #[test]
+    fn test_zigzag_delta() {
+        const W: usize = 5;
+        // A sawtooth that steps up and down by at most 10.
+        let mut values: [u32; 1024] = [0; 1024];
+        for i in 0..1024 {
+            values[i] = 1000 + (i % 6) as u32 - if i % 12 < 6 { 0 } else { 5 };
+        }
+
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+
+        let base = [1000; 32];
+        let mut deltas = [0; 1024];
+        Delta::zigzag_delta(&transposed, &base, &mut deltas);
+        assert!(deltas.iter().all(|&d| d < (1 << W)));
+
+        let mut packed = [0; 128 * W / size_of::<u32>()];
+        BitPacking::pack::<W>(&deltas, &mut packed);
+
+        // Fused kernel
+        let mut unpacked = [0; 1024];
+        Delta::unzigzag_delta_pack::<W>(&packed, &base, &mut unpacked);
+        assert_eq!(transposed, unpacked);
+
+        // Unfused kernel
+        let mut undelta = [0; 1024];
+        Delta::unzigzag_delta(&deltas, &base, &mut undelta);
+        assert_eq!(transposed, undelta);
+    }
 }
@@ -0,0 +1,174 @@
+#![allow(unused_assignments)]
+
+use crate::{iterate, unpack, BitPackWidth, BitPacking, FastLanes, SupportedBitPackWidth};
+use paste::paste;
+
+/// Second-order delta encoding over the transposed layout.
+///
+/// Each lane tracks both the previous value (seeded from `base`) and the previous delta (seeded
+/// from `base_delta`). The difference between consecutive deltas is zigzag-encoded, so a series
+/// with a fixed cadence and a little jitter in either direction packs into very few bits.
+pub trait DeltaOfDelta: BitPacking {
+    fn delta_of_delta(
+        input: &[Self; 1024],
+        base: &[Self; Self::LANES],
+        base_delta: &[Self; Self::LANES],
+        output: &mut [Self; 1024],
+    );
+
+    fn undelta_of_delta(
+        input: &[Self; 1024],
+        base: &[Self; Self::LANES],
+        base_delta: &[Self; Self::LANES],
+        output: &mut [Self; 1024],
+    );
+
+    fn undelta_of_delta_pack<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        base_delta: &[Self; Self::LANES],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+}
+
+macro_rules! impl_delta_of_delta {
+    ($T:ty) => {
+        paste! {
+            impl DeltaOfDelta for $T {
+                #[inline(never)]
+                fn delta_of_delta(
+                    input: &[Self; 1024],
+                    base: &[Self; Self::LANES],
+                    base_delta: &[Self; Self::LANES],
+                    output: &mut [Self; 1024],
+                ) {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        let mut prev_delta = base_delta[lane];
+                        iterate!($T, lane, |$idx| {
+                            let next = input[$idx];
+                            let delta = next.wrapping_sub(prev);
+                            let dd = delta.wrapping_sub(prev_delta);
+                            output[$idx] = (dd << 1) ^ (dd >> (Self::T - 1)).wrapping_neg();
+                            prev = next;
+                            prev_delta = delta;
+                        });
+                    }
+                }
+
+                #[inline(never)]
+                fn undelta_of_delta(
+                    input: &[Self; 1024],
+                    base: &[Self; Self::LANES],
+                    base_delta: &[Self; Self::LANES],
+                    output: &mut [Self; 1024],
+                ) {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        let mut prev_delta = base_delta[lane];
+                        iterate!($T, lane, |$idx| {
+                            let zigzag = input[$idx];
+                            let dd = (zigzag >> 1) ^ (zigzag & 1).wrapping_neg();
+                            let delta = dd.wrapping_add(prev_delta);
+                            let next = delta.wrapping_add(prev);
+                            output[$idx] = next;
+                            prev = next;
+                            prev_delta = delta;
+                        });
+                    }
+                }
+
+                #[inline(never)]
+                fn undelta_of_delta_pack<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    base: &[Self; Self::LANES],
+                    base_delta: &[Self; Self::LANES],
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        let mut prev_delta = base_delta[lane];
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            // Integrate twice: once to recover the delta, once for the value.
+                            let dd = ($elem >> 1) ^ ($elem & 1).wrapping_neg();
+                            let delta = dd.wrapping_add(prev_delta);
+                            let next = delta.wrapping_add(prev);
+                            output[$idx] = next;
+                            prev = next;
+                            prev_delta = delta;
+                        });
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_delta_of_delta!(u8);
+impl_delta_of_delta!(u16);
+impl_delta_of_delta!(u32);
+impl_delta_of_delta!(u64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Transpose;
+    use core::mem::size_of;
+
+    #[test]
+    fn test_delta_of_delta() {
+        const W: usize = 2;
+        // Timestamps with a 1000 tick cadence, jittering by one tick either way.
+        let mut values: [u64; 1024] = [0; 1024];
+        for i in 0..1024 {
+            values[i] = 1_700_000_000_000 + (i as u64) * 1000 + [0, 1, 0, 0, 0, 0, 1, 0][i % 8];
+        }
+
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+
+        // Each lane starts one cadence step after its base.
+        let mut base = [0; 16];
+        for lane in 0..16 {
+            base[lane] = transposed[lane] - 1000;
+        }
+        let base_delta = [1000; 16];
+
+        let mut deltas = [0; 1024];
+        DeltaOfDelta::delta_of_delta(&transposed, &base, &base_delta, &mut deltas);
+        assert!(deltas.iter().all(|&d| d < (1 << W)));
+
+        let mut packed = [0; 128 * W / size_of::<u64>()];
+        BitPacking::pack::<W>(&deltas, &mut packed);
+
+        // Fused kernel
+        let mut unpacked = [0; 1024];
+        DeltaOfDelta::undelta_of_delta_pack::<W>(&packed, &base, &base_delta, &mut unpacked);
+        assert_eq!(transposed, unpacked);
+
+        // Unfused kernel
+        let mut undelta = [0; 1024];
+        DeltaOfDelta::undelta_of_delta(&deltas, &base, &base_delta, &mut undelta);
+        assert_eq!(transposed, undelta);
+    }
+
+    #[test]
+    fn test_delta_of_delta_round_trip() {
+        let values: [u8; 1024] = core::array::from_fn(|i| (i * i % 251) as u8);
+        let base = [3; 128];
+        let base_delta = [7; 128];
+
+        let mut deltas = [0; 1024];
+        DeltaOfDelta::delta_of_delta(&values, &base, &base_delta, &mut deltas);
+
+        let mut packed = [0; 1024];
+        BitPacking::pack::<8>(&deltas, &mut packed);
+
+        let mut unpacked = [0; 1024];
+        DeltaOfDelta::undelta_of_delta_pack::<8>(&packed, &base, &base_delta, &mut unpacked);
+        assert_eq!(values, unpacked);
+    }
+}
@@ -0,0 +1,167 @@
+use num_traits::WrappingAdd;
+
+use crate::{index_of, lane_of_run, BitPackWidth, Delta, SupportedBitPackWidth};
+
+/// Search within a sorted, transposed chunk that has been delta-encoded and packed into `W` bits.
+///
+/// Each lane of the transposed layout holds a contiguous run of `T` logical values, so the
+/// search only needs the first value of a handful of lanes to pick the lane holding the answer,
+/// and then decodes that lane until the answer is found.
+///
+/// The bases must not exceed the first value of their lane, as is the case when they are taken
+/// from the previous chunk of a sorted stream.
+pub trait DeltaSearch: Delta {
+    /// Returns the logical index of the first value that is not less than `value`, or 1024 if
+    /// there is none.
+    fn lower_bound<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        value: Self,
+    ) -> usize
+    where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Returns the logical index of the first value that is greater than `value`, or 1024 if
+    /// there is none.
+    fn upper_bound<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        value: Self,
+    ) -> usize
+    where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+}
+
+impl<T: Delta + WrappingAdd> DeltaSearch for T {
+    fn lower_bound<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        value: Self,
+    ) -> usize
+    where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>,
+    {
+        partition_point::<T, W>(input, base, |v| v >= value)
+    }
+
+    fn upper_bound<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        value: Self,
+    ) -> usize
+    where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>,
+    {
+        partition_point::<T, W>(input, base, |v| v > value)
+    }
+}
+
+// Returns the logical index of the first value satisfying the predicate, which must be
+// monotonic over the sorted chunk.
+fn partition_point<T: Delta + WrappingAdd, const W: usize>(
+    input: &[T; 1024 * W / T::T],
+    base: &[T; T::LANES],
+    pred: impl Fn(T) -> bool,
+) -> usize
+where
+    BitPackWidth<W>: SupportedBitPackWidth<T>,
+{
+    // The value at row 0 of the lane holding logical positions [k * T, (k + 1) * T).
+    let head = |k: usize| -> T {
+        let lane = lane_of_run::<T>(k);
+        if pred(base[lane]) {
+            // The lane's base is no greater than its first value, so we can skip unpacking.
+            return base[lane];
+        }
+        base[lane].wrapping_add(&T::unpack_single::<W>(input, lane))
+    };
+
+    // Binary search for the first run whose head satisfies the predicate.
+    let (mut lo, mut hi) = (0, T::LANES);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(head(mid)) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    if lo == 0 {
+        return 0;
+    }
+
+    // The answer is either within the previous run, or is the head of this run.
+    let k = lo - 1;
+    let lane = lane_of_run::<T>(k);
+    let mut next = base[lane].wrapping_add(&T::unpack_single::<W>(input, lane));
+    for row in 1..T::T {
+        let idx = index_of::<T>(row, lane);
+        next = next.wrapping_add(&T::unpack_single::<W>(input, idx));
+        if pred(next) {
+            return k * T::T + row;
+        }
+    }
+    lo * T::T
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{BitPacking, Transpose};
+    use core::mem::size_of;
+
+    #[test]
+    fn test_bounds() {
+        const W: usize = 3;
+        // Sorted, with runs of duplicates.
+        let values: [u32; 1024] = core::array::from_fn(|i| 100 + (i / 3 * 5) as u32);
+
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+
+        // Use each lane's first value as its base, keeping the first delta of each lane small.
+        let base: [u32; 32] = core::array::from_fn(|lane| transposed[lane]);
+        let mut deltas = [0; 1024];
+        Delta::delta(&transposed, &base, &mut deltas);
+        let mut packed = [0; 128 * W / size_of::<u32>()];
+        BitPacking::pack::<W>(&deltas, &mut packed);
+
+        for x in 95..1900 {
+            assert_eq!(
+                DeltaSearch::lower_bound::<W>(&packed, &base, x),
+                values.partition_point(|&v| v < x),
+                "lower_bound({x})"
+            );
+            assert_eq!(
+                DeltaSearch::upper_bound::<W>(&packed, &base, x),
+                values.partition_point(|&v| v <= x),
+                "upper_bound({x})"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bounds_u8() {
+        let values: [u8; 1024] = core::array::from_fn(|i| (i / 4) as u8);
+
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+
+        let base = [0; 128];
+        let mut deltas = [0; 1024];
+        Delta::delta(&transposed, &base, &mut deltas);
+        let mut packed = [0; 1024];
+        BitPacking::pack::<8>(&deltas, &mut packed);
+
+        for x in 0..=255 {
+            assert_eq!(
+                DeltaSearch::lower_bound::<8>(&packed, &base, x),
+                values.partition_point(|&v| v < x)
+            );
+            assert_eq!(
+                DeltaSearch::upper_bound::<8>(&packed, &base, x),
+                values.partition_point(|&v| v <= x)
+            );
+        }
+    }
+}
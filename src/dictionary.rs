@@ -0,0 +1,282 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use arrayref::{array_mut_ref, array_ref};
+use core::iter::successors;
+use core::mem::size_of;
+use paste::paste;
+
+use crate::{seq_t, unpack, BitPackWidth, BitPacking, Constant, FastLanes, SupportedBitPackWidth};
+
+/// The distinct values of a chunk or column, each identified by its position as a code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dict<T> {
+    values: Vec<T>,
+    codes: BTreeMap<T, T>,
+}
+
+impl<T: FastLanes> Dict<T> {
+    /// Builds a dictionary of the distinct values in ascending order, such that codes compare
+    /// like the values they stand for.
+    #[must_use]
+    pub fn sorted(input: &[T]) -> Self {
+        let mut values = input.to_vec();
+        values.sort_unstable();
+        values.dedup();
+        Self::from_values(values)
+    }
+
+    /// Builds a dictionary of the distinct values in the order they first appear.
+    #[must_use]
+    pub fn insertion_order(input: &[T]) -> Self {
+        let mut seen = BTreeSet::new();
+        let values = input
+            .iter()
+            .copied()
+            .filter(|&value| seen.insert(value))
+            .collect();
+        Self::from_values(values)
+    }
+
+    fn from_values(values: Vec<T>) -> Self {
+        // There are at most `T::MAX + 1` distinct values, so the codes never run out.
+        let codes = successors(Some(T::zero()), |code| code.checked_add(&T::one()));
+        let codes = values.iter().copied().zip(codes).collect();
+        Self { values, codes }
+    }
+
+    /// The distinct values, indexed by code.
+    #[must_use]
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the code of the value, if it is in the dictionary.
+    #[must_use]
+    pub fn code(&self, value: T) -> Option<T> {
+        self.codes.get(&value).copied()
+    }
+
+    /// The number of bits needed for the largest code.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        (usize::BITS - self.len().saturating_sub(1).leading_zeros()) as usize
+    }
+
+    /// The number of `T` words a chunk of codes packs into.
+    #[must_use]
+    pub fn packed_len(&self) -> usize {
+        1024 * self.width() / T::T
+    }
+
+    /// Returns the constant if the dictionary holds a single value.
+    #[must_use]
+    pub fn constant(&self) -> Option<Constant<T>> {
+        match self.values() {
+            &[value] => Some(Constant::Value(value)),
+            _ => None,
+        }
+    }
+}
+
+/// Dictionary encoding, replacing each value with its code in a [`Dict`] and bit-packing the
+/// codes into the dictionary's width.
+pub trait Dictionary: BitPacking {
+    /// Packs the code of each value into `dict.width()` bits.
+    ///
+    /// # Errors
+    /// The index of the first value missing from the dictionary, in which case nothing is written.
+    fn dict_pack(
+        dict: &Dict<Self>,
+        input: &[Self; 1024],
+        output: &mut [Self; 1024],
+    ) -> Result<(), usize>;
+
+    /// Unpacks 1024 `W` bit codes, writing the dictionary value of each.
+    fn undict_pack<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        dict: &[Self],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Unpacks 1024 `W` bit codes, writing the dictionary value of each, where `W` is
+    /// runtime-known instead of compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of length `1024 * W / T`, where `T` is the bit-width of Self and `W`
+    /// is the packed width. The output slice must be of exactly length 1024.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_undict_pack(
+        width: usize,
+        input: &[Self],
+        dict: &[Self],
+        output: &mut [Self],
+    );
+
+    /// Decodes 1024 elements encoded by [`Dictionary::dict_pack`].
+    ///
+    /// # Panics
+    /// If the input is not exactly `dict.packed_len()` words long.
+    fn undict_pack_auto(dict: &Dict<Self>, input: &[Self], output: &mut [Self; 1024]);
+}
+
+macro_rules! impl_dictionary {
+    ($T:ty) => {
+        paste! {
+            impl Dictionary for $T {
+                fn dict_pack(
+                    dict: &Dict<Self>,
+                    input: &[Self; 1024],
+                    output: &mut [Self; 1024],
+                ) -> Result<(), usize> {
+                    let mut codes = [0; 1024];
+                    for (i, &value) in input.iter().enumerate() {
+                        codes[i] = dict.code(value).ok_or(i)?;
+                    }
+                    unsafe { Self::unchecked_pack(dict.width(), &codes, &mut output[..dict.packed_len()]) };
+                    Ok(())
+                }
+
+                fn undict_pack<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    dict: &[Self],
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            output[$idx] = dict[$elem as usize];
+                        });
+                    }
+                }
+
+                unsafe fn unchecked_undict_pack(width: usize, input: &[Self], dict: &[Self], output: &mut [Self]) {
+                    let packed_len = 128 * width / size_of::<Self>();
+                    debug_assert_eq!(input.len(), packed_len, "Input buffer must be of size 1024 * W / T");
+                    debug_assert_eq!(output.len(), 1024, "Output buffer must be of size 1024");
+                    debug_assert!(width <= Self::T, "Width must be less than or equal to {}", Self::T);
+
+                    seq_t!(W in $T {
+                        match width {
+                            #(W => Self::undict_pack::<W>(
+                                array_ref![input, 0, 1024 * W / <$T>::T],
+                                dict,
+                                array_mut_ref![output, 0, 1024],
+                            ),)*
+                            // seq_t has exclusive upper bound
+                            Self::T => Self::undict_pack::<{ Self::T }>(
+                                array_ref![input, 0, 1024],
+                                dict,
+                                array_mut_ref![output, 0, 1024],
+                            ),
+                            _ => unreachable!("Unsupported width: {}", width)
+                        }
+                    })
+                }
+
+                fn undict_pack_auto(dict: &Dict<Self>, input: &[Self], output: &mut [Self; 1024]) {
+                    assert_eq!(input.len(), dict.packed_len(), "Input must be the packed length of the dictionary");
+                    unsafe { Self::unchecked_undict_pack(dict.width(), input, dict.values(), output) };
+                }
+            }
+        }
+    };
+}
+
+impl_dictionary!(u8);
+impl_dictionary!(u16);
+impl_dictionary!(u32);
+impl_dictionary!(u64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sorted_dict() {
+        let values: [u64; 1024] = core::array::from_fn(|i| [900, 10, 5_000_000, 10, 42][i % 5]);
+        let dict = Dict::sorted(&values);
+        assert_eq!(dict.values(), [10, 42, 900, 5_000_000]);
+        assert_eq!(dict.code(900), Some(2));
+        assert_eq!(dict.code(11), None);
+        assert_eq!(dict.width(), 2);
+
+        let mut packed = [0; 1024];
+        Dictionary::dict_pack(&dict, &values, &mut packed).unwrap();
+
+        let mut decoded = [0; 1024];
+        Dictionary::undict_pack::<2>(array_ref![packed, 0, 32], dict.values(), &mut decoded);
+        assert_eq!(decoded, values);
+    }
+
+    #[test]
+    fn test_insertion_order_column() {
+        // A dictionary built over a whole column codes each of its chunks.
+        let column: Vec<u16> = (0..4096u16).map(|i| (i * 7) % 300 + 1000).collect();
+        let dict = Dict::insertion_order(&column);
+        assert_eq!(&dict.values()[..3], [1000, 1007, 1014]);
+        assert_eq!(dict.len(), 300);
+        assert_eq!(dict.width(), 9);
+
+        for chunk in column.chunks_exact(1024) {
+            let chunk = array_ref![chunk, 0, 1024];
+            let mut packed = [0; 1024];
+            Dictionary::dict_pack(&dict, chunk, &mut packed).unwrap();
+
+            let mut decoded = [0; 1024];
+            Dictionary::undict_pack_auto(&dict, &packed[..dict.packed_len()], &mut decoded);
+            assert_eq!(&decoded, chunk);
+        }
+    }
+
+    #[test]
+    fn test_single_value() {
+        let dict = Dict::sorted(&[7u8; 1024]);
+        assert_eq!(dict.width(), 0);
+        assert_eq!(dict.packed_len(), 0);
+
+        let mut packed = [0; 1024];
+        Dictionary::dict_pack(&dict, &[7; 1024], &mut packed).unwrap();
+        let mut decoded = [0; 1024];
+        Dictionary::undict_pack_auto(&dict, &[], &mut decoded);
+        assert_eq!(decoded, [7; 1024]);
+    }
+
+    #[test]
+    fn test_missing_value() {
+        let dict = Dict::sorted(&[1u32, 2, 3]);
+        let values: [u32; 1024] = core::array::from_fn(|i| (i % 3) as u32 + 1);
+        let mut missing = values;
+        missing[700] = 4;
+
+        let mut packed = [0; 1024];
+        assert_eq!(
+            Dictionary::dict_pack(&dict, &missing, &mut packed),
+            Err(700)
+        );
+        assert_eq!(packed, [0; 1024]);
+        assert_eq!(Dictionary::dict_pack(&dict, &values, &mut packed), Ok(()));
+    }
+
+    #[test]
+    fn test_every_value() {
+        // Every value of the type gets a code, up to the largest.
+        let values: Vec<u8> = (0..=255).rev().collect();
+        let dict = Dict::insertion_order(&values);
+        assert_eq!(dict.len(), 256);
+        assert_eq!(dict.width(), 8);
+        assert_eq!(dict.code(0), Some(255));
+        assert_eq!(dict.code(255), Some(0));
+    }
+}
@@ -1,6 +1,54 @@
-use crate::{pack, unpack, BitPackWidth, BitPacking, FastLanes, SupportedBitPackWidth};
+use arrayref::{array_mut_ref, array_ref};
+use core::mem::size_of;
+use core::ops::Range;
 use paste::paste;
 
+use crate::{
+    iterate, pack, seq_t, unpack, BitPackWidth, BitPacking, Constant, FastLanes, FastLanesOrder,
+    IdentityOrder, LaneOrder, SupportedBitPackWidth,
+};
+
+/// The reference and bit-width chosen to FoR-encode a chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FoRHeader<T> {
+    pub reference: T,
+    pub width: usize,
+}
+
+impl<T: FastLanes> FoRHeader<T> {
+    /// Computes the minimum reference and width that losslessly encode the chunk.
+    pub fn compute(input: &[T; 1024]) -> Self {
+        let mut min = T::max_value();
+        let mut max = T::min_value();
+        for &value in input {
+            min = min.min(value);
+            max = max.max(value);
+        }
+        Self {
+            reference: min,
+            width: T::T - (max - min).leading_zeros() as usize,
+        }
+    }
+
+    /// The number of `T` words the chunk packs into.
+    pub fn packed_len(&self) -> usize {
+        1024 * self.width / T::T
+    }
+
+    /// Returns the constant if the chunk packs into zero bits, i.e. every element is the
+    /// reference.
+    #[must_use]
+    pub fn constant(&self) -> Option<Constant<T>> {
+        (self.width == 0).then_some(Constant::Value(self.reference))
+    }
+}
+
+/// Frame-of-reference encoding, subtracting a reference from each value and bit-packing the
+/// remainder.
+///
+/// Every kernel is elementwise, so the packing kernels can lay the values out in any
+/// [`LaneOrder`]. The default `FastLanesOrder` matches `BitPacking::pack`, while [`IdentityOrder`]
+/// keeps each lane's rows in linear order.
 pub trait FoR: BitPacking {
     fn for_pack<const W: usize>(
         input: &[Self; 1024],
@@ -9,12 +57,126 @@ pub trait FoR: BitPacking {
     ) where
         BitPackWidth<W>: SupportedBitPackWidth<Self>;
 
+    /// FoR-encodes 1024 elements into `W` bits each, packed in the lane order `O`.
+    fn for_pack_ordered<const W: usize, O: LaneOrder>(
+        input: &[Self; 1024],
+        reference: Self,
+        output: &mut [Self; 1024 * W / Self::T],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// FoR-encodes 1024 elements into `W` bits each, where `W` is runtime-known instead of
+    /// compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of exactly length 1024. The output slice must be of length
+    /// `1024 * W / T`, where `T` is the bit-width of Self and `W` is the packed width.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_for_pack(
+        width: usize,
+        input: &[Self],
+        reference: Self,
+        output: &mut [Self],
+    );
+
     fn unfor_pack<const W: usize>(
         input: &[Self; 1024 * W / Self::T],
         reference: Self,
         output: &mut [Self; 1024],
     ) where
         BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// FoR-decodes 1024 elements from `W` bits each, packed in the lane order `O`.
+    fn unfor_pack_ordered<const W: usize, O: LaneOrder>(
+        input: &[Self; 1024 * W / Self::T],
+        reference: Self,
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// FoR-decodes 1024 elements from `W` bits each, where `W` is runtime-known instead of
+    /// compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of length `1024 * W / T`, where `T` is the bit-width of Self and `W`
+    /// is the packed width. The output slice must be of exactly length 1024.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_unfor_pack(
+        width: usize,
+        input: &[Self],
+        reference: Self,
+        output: &mut [Self],
+    );
+
+    /// Subtracts the reference from each element without packing the result.
+    fn for_encode(input: &[Self; 1024], reference: Self, output: &mut [Self; 1024]);
+
+    /// Adds the reference back to each element of an unpacked FoR-encoded array.
+    fn for_decode(input: &[Self; 1024], reference: Self, output: &mut [Self; 1024]);
+
+    /// Subtracts the reference from each element in place.
+    fn for_encode_in_place(values: &mut [Self; 1024], reference: Self);
+
+    /// Adds the reference back to each element in place.
+    fn for_decode_in_place(values: &mut [Self; 1024], reference: Self);
+
+    /// FoR-encodes 1024 elements using the smallest reference and width that fit them.
+    fn for_pack_auto(input: &[Self; 1024], output: &mut [Self; 1024]) -> FoRHeader<Self>;
+
+    /// Decodes 1024 elements encoded by [`FoR::for_pack_auto`].
+    ///
+    /// # Panics
+    /// If the input is not exactly `header.packed_len()` words long.
+    fn unfor_pack_auto(header: &FoRHeader<Self>, input: &[Self], output: &mut [Self; 1024]);
+
+    /// Decodes a single element at the provided index from a FoR-packed array of 1024 `W` bit
+    /// elements.
+    fn unfor_single<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        reference: Self,
+        index: usize,
+    ) -> Self
+    where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Decodes a single element at the provided index from a FoR-packed array of 1024 `W` bit
+    /// elements, where `W` is runtime-known instead of compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of length `1024 * W / T`, where `T` is the bit-width of Self and `W`
+    /// is the packed width.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_unfor_single(
+        width: usize,
+        input: &[Self],
+        reference: Self,
+        index: usize,
+    ) -> Self;
+
+    /// Decodes the elements at the indices in `range` from a FoR-packed array of 1024 `W` bit
+    /// elements. The output slice must have the same length as the range.
+    fn unfor_range<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        reference: Self,
+        range: Range<usize>,
+        output: &mut [Self],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Decodes the elements at the indices in `range` from a FoR-packed array of 1024 `W` bit
+    /// elements, where `W` is runtime-known instead of compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of length `1024 * W / T`, where `T` is the bit-width of Self and `W`
+    /// is the packed width. The output slice must have the same length as the range.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_unfor_range(
+        width: usize,
+        input: &[Self],
+        reference: Self,
+        range: Range<usize>,
+        output: &mut [Self],
+    );
 }
 
 macro_rules! impl_for {
@@ -27,27 +189,217 @@ macro_rules! impl_for {
                     output: &mut [Self; 1024 * W / Self::T],
                 ) where
                     BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    Self::for_pack_ordered::<W, FastLanesOrder>(input, reference, output);
+                }
+
+                fn for_pack_ordered<const W: usize, O: LaneOrder>(
+                    input: &[Self; 1024],
+                    reference: Self,
+                    output: &mut [Self; 1024 * W / Self::T],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
                 {
                     for lane in 0..Self::LANES {
-                        pack!($T, W, output, lane, |$idx| {
+                        pack!($T, O, W, output, lane, |$idx| {
                             input[$idx].wrapping_sub(reference)
                         });
                     }
                 }
 
+                unsafe fn unchecked_for_pack(width: usize, input: &[Self], reference: Self, output: &mut [Self]) {
+                    let packed_len = 128 * width / size_of::<Self>();
+                    debug_assert_eq!(output.len(), packed_len, "Output buffer must be of size 1024 * W / T");
+                    debug_assert_eq!(input.len(), 1024, "Input buffer must be of size 1024");
+                    debug_assert!(width <= Self::T, "Width must be less than or equal to {}", Self::T);
+
+                    seq_t!(W in $T {
+                        match width {
+                            #(W => Self::for_pack::<W>(
+                                array_ref![input, 0, 1024],
+                                reference,
+                                array_mut_ref![output, 0, 1024 * W / <$T>::T],
+                            ),)*
+                            // seq_t has exclusive upper bound
+                            Self::T => Self::for_pack::<{ Self::T }>(
+                                array_ref![input, 0, 1024],
+                                reference,
+                                array_mut_ref![output, 0, 1024],
+                            ),
+                            _ => unreachable!("Unsupported width: {}", width)
+                        }
+                    })
+                }
+
                 fn unfor_pack<const W: usize>(
                     input: &[Self; 1024 * W / Self::T],
                     reference: Self,
                     output: &mut [Self; 1024],
                 ) where
                     BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    Self::unfor_pack_ordered::<W, FastLanesOrder>(input, reference, output);
+                }
+
+                fn unfor_pack_ordered<const W: usize, O: LaneOrder>(
+                    input: &[Self; 1024 * W / Self::T],
+                    reference: Self,
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
                 {
                     for lane in 0..Self::LANES {
-                        unpack!($T, W, input, lane, |$idx, $elem| {
+                        unpack!($T, O, W, input, lane, |$idx, $elem| {
                             output[$idx] = $elem.wrapping_add(reference)
                         });
                     }
                 }
+
+                unsafe fn unchecked_unfor_pack(width: usize, input: &[Self], reference: Self, output: &mut [Self]) {
+                    let packed_len = 128 * width / size_of::<Self>();
+                    debug_assert_eq!(input.len(), packed_len, "Input buffer must be of size 1024 * W / T");
+                    debug_assert_eq!(output.len(), 1024, "Output buffer must be of size 1024");
+                    debug_assert!(width <= Self::T, "Width must be less than or equal to {}", Self::T);
+
+                    seq_t!(W in $T {
+                        match width {
+                            #(W => Self::unfor_pack::<W>(
+                                array_ref![input, 0, 1024 * W / <$T>::T],
+                                reference,
+                                array_mut_ref![output, 0, 1024],
+                            ),)*
+                            // seq_t has exclusive upper bound
+                            Self::T => Self::unfor_pack::<{ Self::T }>(
+                                array_ref![input, 0, 1024],
+                                reference,
+                                array_mut_ref![output, 0, 1024],
+                            ),
+                            _ => unreachable!("Unsupported width: {}", width)
+                        }
+                    })
+                }
+
+                fn for_encode(input: &[Self; 1024], reference: Self, output: &mut [Self; 1024]) {
+                    for lane in 0..Self::LANES {
+                        iterate!($T, IdentityOrder, lane, |$idx| {
+                            output[$idx] = input[$idx].wrapping_sub(reference);
+                        });
+                    }
+                }
+
+                fn for_decode(input: &[Self; 1024], reference: Self, output: &mut [Self; 1024]) {
+                    for lane in 0..Self::LANES {
+                        iterate!($T, IdentityOrder, lane, |$idx| {
+                            output[$idx] = input[$idx].wrapping_add(reference);
+                        });
+                    }
+                }
+
+                fn for_encode_in_place(values: &mut [Self; 1024], reference: Self) {
+                    for lane in 0..Self::LANES {
+                        iterate!($T, IdentityOrder, lane, |$idx| {
+                            values[$idx] = values[$idx].wrapping_sub(reference);
+                        });
+                    }
+                }
+
+                fn for_decode_in_place(values: &mut [Self; 1024], reference: Self) {
+                    for lane in 0..Self::LANES {
+                        iterate!($T, IdentityOrder, lane, |$idx| {
+                            values[$idx] = values[$idx].wrapping_add(reference);
+                        });
+                    }
+                }
+
+                fn for_pack_auto(input: &[Self; 1024], output: &mut [Self; 1024]) -> FoRHeader<Self> {
+                    let header = FoRHeader::compute(input);
+                    unsafe {
+                        Self::unchecked_for_pack(
+                            header.width,
+                            input,
+                            header.reference,
+                            &mut output[..header.packed_len()],
+                        );
+                    }
+                    header
+                }
+
+                fn unfor_pack_auto(header: &FoRHeader<Self>, input: &[Self], output: &mut [Self; 1024]) {
+                    assert_eq!(input.len(), header.packed_len(), "Input must be the packed length of the header");
+                    unsafe { Self::unchecked_unfor_pack(header.width, input, header.reference, output) };
+                }
+
+                fn unfor_single<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    reference: Self,
+                    index: usize,
+                ) -> Self
+                where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    Self::unpack_single::<W>(input, index).wrapping_add(reference)
+                }
+
+                unsafe fn unchecked_unfor_single(
+                    width: usize,
+                    input: &[Self],
+                    reference: Self,
+                    index: usize,
+                ) -> Self {
+                    unsafe { Self::unchecked_unpack_single(width, input, index) }.wrapping_add(reference)
+                }
+
+                fn unfor_range<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    reference: Self,
+                    range: Range<usize>,
+                    output: &mut [Self],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    assert!(range.end <= 1024, "Range must end at or before 1024, got {}", range.end);
+                    assert_eq!(output.len(), range.len(), "Output must be the same length as the range");
+                    if range.len() == 1024 {
+                        // Decoding everything is cheaper with the bulk kernel.
+                        return Self::unfor_pack::<W>(input, reference, array_mut_ref![output, 0, 1024]);
+                    }
+                    for (index, out) in range.zip(output.iter_mut()) {
+                        *out = Self::unpack_single::<W>(input, index).wrapping_add(reference);
+                    }
+                }
+
+                unsafe fn unchecked_unfor_range(
+                    width: usize,
+                    input: &[Self],
+                    reference: Self,
+                    range: Range<usize>,
+                    output: &mut [Self],
+                ) {
+                    const T: usize = <$T>::T;
+
+                    let packed_len = 128 * width / size_of::<Self>();
+                    debug_assert_eq!(input.len(), packed_len, "Input buffer must be of size {}", packed_len);
+                    debug_assert!(width <= Self::T, "Width must be less than or equal to {}", Self::T);
+
+                    seq_t!(W in $T {
+                        match width {
+                            #(W => Self::unfor_range::<W>(
+                                array_ref![input, 0, 1024 * W / T],
+                                reference,
+                                range,
+                                output,
+                            ),)*
+                            // seq_t has exclusive upper bound
+                            T => Self::unfor_range::<T>(
+                                array_ref![input, 0, 1024],
+                                reference,
+                                range,
+                                output,
+                            ),
+                            _ => unreachable!("Unsupported width: {}", width)
+                        }
+                    })
+                }
             }
         }
     };
@@ -61,7 +413,6 @@ impl_for!(u64);
 #[cfg(test)]
 mod test {
     use super::*;
-    use core::mem::size_of;
 
     #[test]
     fn test_ffor() {
@@ -86,4 +437,123 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_unfor_single_and_range() {
+        const W: usize = 9;
+        let values: [u32; 1024] = core::array::from_fn(|i| 5000 + (i * 13 % 500) as u32);
+
+        let mut packed = [0; 128 * W / size_of::<u32>()];
+        FoR::for_pack::<W>(&values, 5000, &mut packed);
+
+        for i in 0..1024 {
+            assert_eq!(FoR::unfor_single::<W>(&packed, 5000, i), values[i]);
+            assert_eq!(
+                unsafe { FoR::unchecked_unfor_single(W, &packed, 5000, i) },
+                values[i]
+            );
+        }
+
+        for range in [0..0, 0..1, 10..100, 1000..1024, 0..1024] {
+            let mut output = [0; 1024];
+            FoR::unfor_range::<W>(&packed, 5000, range.clone(), &mut output[..range.len()]);
+            assert_eq!(&output[..range.len()], &values[range.clone()]);
+
+            let mut output = [0; 1024];
+            unsafe {
+                FoR::unchecked_unfor_range(
+                    W,
+                    &packed,
+                    5000,
+                    range.clone(),
+                    &mut output[..range.len()],
+                );
+            };
+            assert_eq!(&output[..range.len()], &values[range]);
+        }
+    }
+
+    #[test]
+    fn test_for_pack_auto() {
+        let values: [u64; 1024] = core::array::from_fn(|i| 1 << 40 | (i * 7 % 1000) as u64);
+
+        let mut packed = [0; 1024];
+        let header = FoR::for_pack_auto(&values, &mut packed);
+        assert_eq!(
+            header,
+            FoRHeader {
+                reference: 1 << 40,
+                width: 10
+            }
+        );
+        assert_eq!(header.packed_len(), 160);
+
+        let mut unpacked = [0; 1024];
+        FoR::unfor_pack_auto(&header, &packed[..header.packed_len()], &mut unpacked);
+        assert_eq!(values, unpacked);
+    }
+
+    #[test]
+    fn test_for_pack_auto_extremes() {
+        for values in [[7u8; 1024], core::array::from_fn(|i| i as u8)] {
+            let mut packed = [0; 1024];
+            let header = FoR::for_pack_auto(&values, &mut packed);
+
+            let mut unpacked = [0; 1024];
+            FoR::unfor_pack_auto(&header, &packed[..header.packed_len()], &mut unpacked);
+            assert_eq!(values, unpacked);
+        }
+    }
+
+    #[test]
+    fn test_for_encode() {
+        let values: [u16; 1024] = core::array::from_fn(|i| (i * 3) as u16);
+
+        let mut encoded = [0; 1024];
+        FoR::for_encode(&values, 7, &mut encoded);
+        for i in 0..1024 {
+            assert_eq!(encoded[i], values[i].wrapping_sub(7));
+        }
+
+        let mut decoded = [0; 1024];
+        FoR::for_decode(&encoded, 7, &mut decoded);
+        assert_eq!(values, decoded);
+
+        // Encoding matches packing at full width.
+        let mut packed = [0; 1024];
+        FoR::for_pack::<16>(&values, 7, &mut packed);
+        let mut unpacked = [0; 1024];
+        BitPacking::unpack::<16>(&packed, &mut unpacked);
+        assert_eq!(encoded, unpacked);
+
+        let mut in_place = values;
+        FoR::for_encode_in_place(&mut in_place, 7);
+        assert_eq!(in_place, encoded);
+        FoR::for_decode_in_place(&mut in_place, 7);
+        assert_eq!(in_place, values);
+    }
+
+    #[test]
+    fn test_for_pack_identity_order() {
+        const W: usize = 9;
+        let values: [u32; 1024] = core::array::from_fn(|i| 5000 + (i * 37 % 512) as u32);
+
+        let mut packed = [0; 1024 * W / 32];
+        FoR::for_pack_ordered::<W, IdentityOrder>(&values, 5000, &mut packed);
+
+        let mut decoded = [0; 1024];
+        FoR::unfor_pack_ordered::<W, IdentityOrder>(&packed, 5000, &mut decoded);
+        assert_eq!(decoded, values);
+        for i in 0..1024 {
+            let single = BitPacking::unpack_single_ordered::<W, IdentityOrder>(&packed, i);
+            assert_eq!(single + 5000, values[i]);
+        }
+
+        // At full width, the identity order packs the encoded values as they are.
+        let mut copied = [0; 1024];
+        FoR::for_pack_ordered::<32, IdentityOrder>(&values, 5000, &mut copied);
+        let mut encoded = [0; 1024];
+        FoR::for_encode(&values, 5000, &mut encoded);
+        assert_eq!(copied, encoded);
+    }
 }
@@ -0,0 +1,185 @@
+use crate::{untranspose, FastLanes, FastLanesOrder, LaneOrder};
+
+/// Returns the index of the element at `row` of `lane` in a vector of 1024 `T`s.
+///
+/// This is the index the `iterate!`, `pack!` and `unpack!` kernels visit for that row and lane.
+#[must_use]
+pub const fn index_of<T: FastLanes>(row: usize, lane: usize) -> usize {
+    ordered_index_of::<T, FastLanesOrder>(row, lane)
+}
+
+/// Returns the lane holding the element at `index`.
+#[must_use]
+pub const fn lane_of<T: FastLanes>(index: usize) -> usize {
+    ordered_lane_of::<T, FastLanesOrder>(index)
+}
+
+/// Returns the row of its lane holding the element at `index`.
+#[must_use]
+pub const fn row_of<T: FastLanes>(index: usize) -> usize {
+    ordered_row_of::<T, FastLanesOrder>(index)
+}
+
+/// Returns the lane whose rows hold the logical positions `[k * T, (k + 1) * T)` of a transposed
+/// vector.
+#[must_use]
+pub const fn lane_of_run<T: FastLanes>(k: usize) -> usize {
+    lane_of::<T>(untranspose(k * T::T))
+}
+
+/// Returns the packed word holding the first bit of the element at `index` when packed into
+/// `width` bits, and the position of that bit within the word.
+///
+/// When `bit + width` exceeds `T::T`, the remaining bits start at the bottom of the word
+/// `T::LANES` positions later.
+#[must_use]
+pub const fn packed_offset_of<T: FastLanes>(width: usize, index: usize) -> (usize, usize) {
+    ordered_packed_offset_of::<T, FastLanesOrder>(width, index)
+}
+
+/// Returns the index of the element at `row` of `lane` in a vector of 1024 `T`s laid out in the
+/// lane order `O`.
+#[must_use]
+pub const fn ordered_index_of<T: FastLanes, O: LaneOrder>(row: usize, lane: usize) -> usize {
+    O::ORDER[row / 8] * O::GROUP_STRIDE.of::<T>() + (row % 8) * O::ROW_STRIDE.of::<T>() + lane
+}
+
+/// Returns the lane holding the element at `index` in the lane order `O`.
+#[must_use]
+pub const fn ordered_lane_of<T: FastLanes, O: LaneOrder>(index: usize) -> usize {
+    // Every row starts at a multiple of `T::LANES`.
+    index % T::LANES
+}
+
+/// Returns the row of its lane holding the element at `index` in the lane order `O`.
+#[must_use]
+pub const fn ordered_row_of<T: FastLanes, O: LaneOrder>(index: usize) -> usize {
+    let offset = index - ordered_lane_of::<T, O>(index);
+    let (group_stride, row_stride) = (O::GROUP_STRIDE.of::<T>(), O::ROW_STRIDE.of::<T>());
+    // One stride nests within the other, so the wider one gives the outer digit of the offset.
+    let (position, row) = if group_stride > row_stride {
+        (offset / group_stride, (offset % group_stride) / row_stride)
+    } else {
+        ((offset % row_stride) / group_stride, offset / row_stride)
+    };
+    O::INVERSE_ORDER[position] * 8 + row
+}
+
+/// Returns the packed word and bit offset of the element at `index`, as [`packed_offset_of`],
+/// when packed in the lane order `O`.
+#[must_use]
+pub const fn ordered_packed_offset_of<T: FastLanes, O: LaneOrder>(
+    width: usize,
+    index: usize,
+) -> (usize, usize) {
+    let start_bit = ordered_row_of::<T, O>(index) * width;
+    (
+        T::LANES * (start_bit / T::T) + ordered_lane_of::<T, O>(index),
+        start_bit % T::T,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use core::array;
+
+    use seq_macro::seq;
+
+    use super::*;
+    use crate::test::SpreadOrder;
+    use crate::{
+        transpose, untranspose, BitPackWidth, BitPacking, IdentityOrder, SupportedBitPackWidth,
+    };
+
+    #[test]
+    fn test_lane_and_row_round_trip() {
+        fn check<T: FastLanes, O: LaneOrder>() {
+            for i in 0..1024 {
+                let (lane, row) = (ordered_lane_of::<T, O>(i), ordered_row_of::<T, O>(i));
+                assert!(lane < T::LANES && row < T::T);
+                assert_eq!(ordered_index_of::<T, O>(row, lane), i);
+            }
+        }
+
+        check::<u8, FastLanesOrder>();
+        check::<u16, FastLanesOrder>();
+        check::<u32, FastLanesOrder>();
+        check::<u64, FastLanesOrder>();
+        check::<u8, IdentityOrder>();
+        check::<u16, IdentityOrder>();
+        check::<u32, IdentityOrder>();
+        check::<u64, IdentityOrder>();
+        check::<u8, SpreadOrder>();
+        check::<u16, SpreadOrder>();
+        check::<u32, SpreadOrder>();
+        check::<u64, SpreadOrder>();
+    }
+
+    #[test]
+    fn test_lanes_are_transposed_runs() {
+        fn check<T: FastLanes>() {
+            for lane in 0..T::LANES {
+                let start = transpose(index_of::<T>(0, lane));
+                for row in 0..T::T {
+                    let idx = index_of::<T>(row, lane);
+                    assert_eq!(transpose(idx), start + row);
+                    assert_eq!(untranspose(transpose(idx)), idx);
+                }
+            }
+        }
+
+        check::<u8>();
+        check::<u16>();
+        check::<u32>();
+        check::<u64>();
+    }
+
+    #[test]
+    fn test_lane_of_run() {
+        fn check<T: FastLanes>() {
+            for k in 0..T::LANES {
+                let lane = lane_of_run::<T>(k);
+                for row in 0..T::T {
+                    assert_eq!(transpose(index_of::<T>(row, lane)), k * T::T + row);
+                }
+            }
+        }
+
+        check::<u8>();
+        check::<u16>();
+        check::<u32>();
+        check::<u64>();
+    }
+
+    #[test]
+    fn test_packed_offset() {
+        fn check<T: BitPacking + core::fmt::Debug, const W: usize>()
+        where
+            BitPackWidth<W>: SupportedBitPackWidth<T>,
+            [(); 1024 * W / T::T]:,
+        {
+            let mask = u64::MAX >> (64 - W);
+            let values: [T; 1024] = array::from_fn(|i| {
+                T::from((i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) & mask).unwrap()
+            });
+            let mut packed = [T::zero(); 1024 * W / T::T];
+            BitPacking::pack::<W>(&values, &mut packed);
+
+            let mask = T::from(mask).unwrap();
+            for i in 0..1024 {
+                let (word, bit) = packed_offset_of::<T>(W, i);
+                let mut value = packed[word] >> bit;
+                if bit + W > T::T {
+                    value = value | (packed[word + T::LANES] << (T::T - bit));
+                }
+                assert_eq!(value & mask, values[i]);
+                assert_eq!(value & mask, BitPacking::unpack_single::<W>(&packed, i));
+            }
+        }
+
+        seq!(W in 1..=8 { check::<u8, W>(); });
+        seq!(W in 1..=16 { check::<u16, W>(); });
+        seq!(W in 1..=32 { check::<u32, W>(); });
+        seq!(W in 1..=64 { check::<u64, W>(); });
+    }
+}
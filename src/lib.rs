@@ -8,19 +8,119 @@ extern crate core;
 use core::mem::size_of;
 use num_traits::{PrimInt, Unsigned};
 
+mod alp;
+mod alp_rd;
 mod bitpacking;
+mod constant;
 mod delta;
+mod delta_of_delta;
+mod delta_search;
+mod dictionary;
 mod ffor;
+mod layout;
+mod linear;
 mod macros;
+mod nullable;
+mod pfor;
+mod rle;
+mod stream;
 mod transpose;
+mod xor_delta;
 
+pub use alp::*;
+pub use alp_rd::*;
 pub use bitpacking::*;
+pub use constant::*;
 pub use delta::*;
+pub use delta_of_delta::*;
+pub use delta_search::*;
+pub use dictionary::*;
 pub use ffor::*;
+pub use layout::*;
+pub use linear::*;
+pub use nullable::*;
+pub use pfor::*;
+pub use rle::*;
+pub use stream::*;
 pub use transpose::*;
+pub use xor_delta::*;
 
 pub const FL_ORDER: [usize; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
 
+/// The layout of the rows of each lane across a vector of 1024 elements.
+///
+/// The kernel macros iterate every lane row by row, and the order maps each (row, lane) pair to
+/// the index it reads from or writes to, as given by [`ordered_index_of`]. Each lane's rows are
+/// split into groups of eight, and row `row` of `lane` is at
+///
+/// `ORDER[row / 8] * GROUP_STRIDE + (row % 8) * ROW_STRIDE + lane`
+///
+/// The order is described by associated constants rather than methods so that the mapping
+/// remains a `const fn`. For it to be invertible, one stride must be eight times the other or
+/// more, and every row must start at a multiple of `T::LANES`.
+pub trait LaneOrder {
+    /// The position of each group of eight rows.
+    const ORDER: [usize; 8];
+    /// The distance between consecutive group positions.
+    const GROUP_STRIDE: Stride;
+    /// The distance between consecutive rows of a group.
+    const ROW_STRIDE: Stride;
+    /// The group at each position, i.e. the inverse permutation of `ORDER`.
+    const INVERSE_ORDER: [usize; 8] = invert_order(Self::ORDER);
+}
+
+/// A distance between the indices of a vector of 1024 elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stride {
+    /// A fixed number of elements, whatever their type.
+    Elements(usize),
+    /// A multiple of the number of lanes, `T::LANES`.
+    Lanes(usize),
+}
+
+impl Stride {
+    /// The number of elements this stride spans for vectors of `T`.
+    #[must_use]
+    pub const fn of<T: FastLanes>(self) -> usize {
+        match self {
+            Stride::Elements(n) => n,
+            Stride::Lanes(n) => n * T::LANES,
+        }
+    }
+}
+
+const fn invert_order(order: [usize; 8]) -> [usize; 8] {
+    let mut inverse = [0; 8];
+    let mut group = 0;
+    while group < 8 {
+        inverse[order[group]] = group;
+        group += 1;
+    }
+    inverse
+}
+
+/// The interleaving of the `FastLanes` paper, which groups each lane's rows in eights laid out
+/// by `FL_ORDER`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FastLanesOrder;
+
+impl LaneOrder for FastLanesOrder {
+    const ORDER: [usize; 8] = FL_ORDER;
+    const GROUP_STRIDE: Stride = Stride::Elements(16);
+    const ROW_STRIDE: Stride = Stride::Elements(128);
+}
+
+/// Rows laid out one after another, for kernels such as `FoR` that never depend on the
+/// transposed layout. Packing at full width is then a plain copy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IdentityOrder;
+
+impl LaneOrder for IdentityOrder {
+    const ORDER: [usize; 8] = [0, 1, 2, 3, 4, 5, 6, 7];
+    const GROUP_STRIDE: Stride = Stride::Lanes(8);
+    const ROW_STRIDE: Stride = Stride::Lanes(1);
+}
+
 pub trait FastLanes: Sized + Unsigned + PrimInt {
     const T: usize = size_of::<Self>() * 8;
     const LANES: usize = 1024 / Self::T;
@@ -31,6 +131,44 @@ impl FastLanes for u16 {}
 impl FastLanes for u32 {}
 impl FastLanes for u64 {}
 
+/// Equality of bit patterns, under which `-0.0` differs from `0.0` and a NaN equals itself.
+///
+/// Encodings that collapse equal values must use this rather than `PartialEq`, so that decoding
+/// reproduces every input bit for bit.
+pub trait BitEq: Copy {
+    fn bit_eq(self, other: Self) -> bool;
+}
+
+macro_rules! impl_bit_eq {
+    ($T:ty) => {
+        impl BitEq for $T {
+            #[inline]
+            fn bit_eq(self, other: Self) -> bool {
+                self == other
+            }
+        }
+    };
+    ($F:ty, to_bits) => {
+        impl BitEq for $F {
+            #[inline]
+            fn bit_eq(self, other: Self) -> bool {
+                self.to_bits() == other.to_bits()
+            }
+        }
+    };
+}
+
+impl_bit_eq!(u8);
+impl_bit_eq!(u16);
+impl_bit_eq!(u32);
+impl_bit_eq!(u64);
+impl_bit_eq!(i8);
+impl_bit_eq!(i16);
+impl_bit_eq!(i32);
+impl_bit_eq!(i64);
+impl_bit_eq!(f32, to_bits);
+impl_bit_eq!(f64, to_bits);
+
 pub struct Pred<const B: bool>;
 
 pub trait Satisfied {}
@@ -48,7 +186,19 @@ macro_rules! seq_t {
 
 #[cfg(test)]
 mod test {
-    use crate::FL_ORDER;
+    use crate::{
+        ordered_index_of, FastLanes, FastLanesOrder, IdentityOrder, LaneOrder, Stride, FL_ORDER,
+    };
+
+    /// A lane order described only by its constants, with the groups placed differently from
+    /// `FL_ORDER`.
+    pub(crate) struct SpreadOrder;
+
+    impl LaneOrder for SpreadOrder {
+        const ORDER: [usize; 8] = [0, 4, 6, 2, 1, 5, 7, 3];
+        const GROUP_STRIDE: Stride = Stride::Elements(16);
+        const ROW_STRIDE: Stride = Stride::Elements(128);
+    }
 
     #[test]
     fn test_ordering_is_own_inverse() {
@@ -57,4 +207,31 @@ mod test {
             assert_eq!(FL_ORDER[FL_ORDER[i]], i);
         }
     }
+
+    #[test]
+    fn test_lane_orders_are_permutations() {
+        fn check<T: FastLanes, O: LaneOrder>() {
+            let mut seen = [false; 1024];
+            for row in 0..T::T {
+                for lane in 0..T::LANES {
+                    let idx = ordered_index_of::<T, O>(row, lane);
+                    assert!(!seen[idx]);
+                    seen[idx] = true;
+                }
+            }
+        }
+
+        check::<u8, FastLanesOrder>();
+        check::<u16, FastLanesOrder>();
+        check::<u32, FastLanesOrder>();
+        check::<u64, FastLanesOrder>();
+        check::<u8, IdentityOrder>();
+        check::<u16, IdentityOrder>();
+        check::<u32, IdentityOrder>();
+        check::<u64, IdentityOrder>();
+        check::<u8, SpreadOrder>();
+        check::<u16, SpreadOrder>();
+        check::<u32, SpreadOrder>();
+        check::<u64, SpreadOrder>();
+    }
 }
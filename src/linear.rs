@@ -0,0 +1,267 @@
+use arrayref::{array_mut_ref, array_ref};
+use core::mem::size_of;
+use paste::paste;
+
+use crate::{seq_t, unpack, BitPackWidth, BitPacking, Constant, FastLanes, SupportedBitPackWidth};
+
+/// The line `base + index * step` that a chunk is encoded against, along with the bit-width of
+/// the residuals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Line<T> {
+    pub base: T,
+    pub step: T,
+    pub width: usize,
+}
+
+impl<T: FastLanes> Line<T> {
+    /// Whether the chunk is exactly the line, leaving no residuals to store.
+    pub fn is_exact(&self) -> bool {
+        self.width == 0
+    }
+
+    /// The number of `T` words the residuals pack into.
+    pub fn packed_len(&self) -> usize {
+        1024 * self.width / T::T
+    }
+
+    /// Returns the constant if the line is flat and exact, i.e. every element is the base.
+    #[must_use]
+    pub fn constant(&self) -> Option<Constant<T>> {
+        (self.step.is_zero() && self.is_exact()).then_some(Constant::Value(self.base))
+    }
+}
+
+/// Linear encoding, modelling each chunk as `base + index * step` and bit-packing the residuals.
+///
+/// Auto-increment keys and evenly spaced timestamps are exactly linear and so have no packed
+/// payload at all.
+pub trait Linear: BitPacking {
+    /// Fits a line to the chunk and packs the residuals against it.
+    ///
+    /// Of the step between the first two values, the mean step and no step at all, i.e. plain
+    /// `FoR`, the line keeps whichever leaves the narrowest residuals.
+    fn linear_pack(input: &[Self; 1024], output: &mut [Self; 1024]) -> Line<Self>;
+
+    /// Unpacks `W` bit residuals and adds each to its position on the line.
+    fn unlinear_pack<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: Self,
+        step: Self,
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Unpacks `W` bit residuals and adds each to its position on the line, where `W` is
+    /// runtime-known instead of compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of length `1024 * W / T`, where `T` is the bit-width of Self and `W`
+    /// is the packed width. The output slice must be of exactly length 1024.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_unlinear_pack(
+        width: usize,
+        input: &[Self],
+        base: Self,
+        step: Self,
+        output: &mut [Self],
+    );
+
+    /// Decodes 1024 elements encoded by [`Linear::linear_pack`].
+    ///
+    /// # Panics
+    /// If the input is not exactly `line.packed_len()` words long.
+    fn unlinear_pack_auto(line: &Line<Self>, input: &[Self], output: &mut [Self; 1024]);
+}
+
+macro_rules! impl_linear {
+    ($T:ty) => {
+        paste! {
+            impl Linear for $T {
+                fn linear_pack(input: &[Self; 1024], output: &mut [Self; 1024]) -> Line<Self> {
+                    // Fits the line with the given step, rebased onto the smallest residual,
+                    // compared as signed offsets from the first residual, such that all packed
+                    // residuals are small and positive.
+                    let sign: $T = 1 << (Self::T - 1);
+                    let fit = |step: $T| {
+                        let mut residuals: [$T; 1024] = core::array::from_fn(|i| {
+                            input[i].wrapping_sub((i as $T).wrapping_mul(step))
+                        });
+                        let pivot = residuals[0];
+                        let min_offset = residuals
+                            .iter()
+                            .map(|r| r.wrapping_sub(pivot) ^ sign)
+                            .min()
+                            .map_or(0, |offset| offset ^ sign);
+                        let base = pivot.wrapping_add(min_offset);
+
+                        let mut max = 0;
+                        for residual in &mut residuals {
+                            *residual = residual.wrapping_sub(base);
+                            max = max.max(*residual);
+                        }
+                        let line = Line {
+                            base,
+                            step,
+                            width: Self::T - max.leading_zeros() as usize,
+                        };
+                        (line, residuals)
+                    };
+
+                    // The step between the first two values detects exact sequences, even when
+                    // they wrap around. The mean step sums each difference as a signed offset, so
+                    // unlike the slope between the endpoints it survives the values wrapping.
+                    let total: i128 = (1..1024)
+                        .map(|i| i128::from(input[i].wrapping_sub(input[i - 1]) ^ sign) - i128::from(sign))
+                        .sum();
+                    let mean = ((total.unsigned_abs() + 511) / 1023) as $T;
+                    let mean = if total < 0 { mean.wrapping_neg() } else { mean };
+
+                    let (mut line, mut residuals) = fit(input[1].wrapping_sub(input[0]));
+                    for step in [mean, 0] {
+                        let candidate = fit(step);
+                        if candidate.0.width < line.width {
+                            (line, residuals) = candidate;
+                        }
+                    }
+                    unsafe {
+                        Self::unchecked_pack(line.width, &residuals, &mut output[..line.packed_len()]);
+                    }
+                    line
+                }
+
+                fn unlinear_pack<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    base: Self,
+                    step: Self,
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            output[$idx] = base.wrapping_add(($idx as $T).wrapping_mul(step)).wrapping_add($elem);
+                        });
+                    }
+                }
+
+                unsafe fn unchecked_unlinear_pack(
+                    width: usize,
+                    input: &[Self],
+                    base: Self,
+                    step: Self,
+                    output: &mut [Self],
+                ) {
+                    let packed_len = 128 * width / size_of::<Self>();
+                    debug_assert_eq!(input.len(), packed_len, "Input buffer must be of size 1024 * W / T");
+                    debug_assert_eq!(output.len(), 1024, "Output buffer must be of size 1024");
+                    debug_assert!(width <= Self::T, "Width must be less than or equal to {}", Self::T);
+
+                    seq_t!(W in $T {
+                        match width {
+                            #(W => Self::unlinear_pack::<W>(
+                                array_ref![input, 0, 1024 * W / <$T>::T],
+                                base,
+                                step,
+                                array_mut_ref![output, 0, 1024],
+                            ),)*
+                            // seq_t has exclusive upper bound
+                            Self::T => Self::unlinear_pack::<{ Self::T }>(
+                                array_ref![input, 0, 1024],
+                                base,
+                                step,
+                                array_mut_ref![output, 0, 1024],
+                            ),
+                            _ => unreachable!("Unsupported width: {}", width)
+                        }
+                    })
+                }
+
+                fn unlinear_pack_auto(line: &Line<Self>, input: &[Self], output: &mut [Self; 1024]) {
+                    assert_eq!(input.len(), line.packed_len(), "Input must be the packed length of the line");
+                    unsafe { Self::unchecked_unlinear_pack(line.width, input, line.base, line.step, output) };
+                }
+            }
+        }
+    };
+}
+
+impl_linear!(u8);
+impl_linear!(u16);
+impl_linear!(u32);
+impl_linear!(u64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exact_sequence() {
+        let values: [u64; 1024] = core::array::from_fn(|i| 1_000_000 + (i as u64) * 1000);
+
+        let mut packed = [0; 1024];
+        let line = Linear::linear_pack(&values, &mut packed);
+        assert_eq!(
+            line,
+            Line {
+                base: 1_000_000,
+                step: 1000,
+                width: 0
+            }
+        );
+        assert!(line.is_exact());
+
+        let mut unpacked = [0; 1024];
+        Linear::unlinear_pack_auto(&line, &[], &mut unpacked);
+        assert_eq!(values, unpacked);
+    }
+
+    #[test]
+    fn test_wrapping_sequences() {
+        // Counts down, wrapping around zero.
+        let values: [u8; 1024] = core::array::from_fn(|i| 5u8.wrapping_sub(i as u8));
+
+        let mut packed = [0; 1024];
+        let line = Linear::linear_pack(&values, &mut packed);
+        assert!(line.is_exact());
+
+        let mut unpacked = [0; 1024];
+        Linear::unlinear_pack::<0>(&[], line.base, line.step, &mut unpacked);
+        assert_eq!(values, unpacked);
+    }
+
+    #[test]
+    fn test_residuals() {
+        // Roughly linear, jittering above and below the line.
+        let values: [u32; 1024] =
+            core::array::from_fn(|i| (50_000 + i * 20 + (i * 7) % 9) as u32 - 4);
+
+        let mut packed = [0; 1024];
+        let line = Linear::linear_pack(&values, &mut packed);
+        assert_eq!(line.step, 20);
+        assert_eq!(line.width, 4);
+
+        let mut unpacked = [0; 1024];
+        Linear::unlinear_pack_auto(&line, &packed[..line.packed_len()], &mut unpacked);
+        assert_eq!(values, unpacked);
+    }
+
+    #[test]
+    fn test_wrapping_residuals() {
+        // The endpoints wrap many times, hiding the slope between them.
+        let values: [u8; 1024] = core::array::from_fn(|i| (i * 3 + i % 5) as u8);
+
+        let mut packed = [0; 1024];
+        let line = Linear::linear_pack(&values, &mut packed);
+        assert_eq!(line.step, 3);
+        assert_eq!(line.width, 3);
+
+        let mut unpacked = [0; 1024];
+        Linear::unlinear_pack_auto(&line, &packed[..line.packed_len()], &mut unpacked);
+        assert_eq!(values, unpacked);
+
+        // Without any slope, the line is plain FoR.
+        let values: [u16; 1024] = core::array::from_fn(|i| [900, 7, 300][i % 3]);
+        let line = Linear::linear_pack(&values, &mut [0; 1024]);
+        assert_eq!((line.base, line.step, line.width), (7, 0, 10));
+    }
+}
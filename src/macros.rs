@@ -7,24 +7,24 @@
 /// be used to easily generated fused kernels with transposed encodings such as delta.
 ///
 /// Essentially this means: BitPack(Delta(Transpose(V))) == Delta+BitPack(Transpose(V))
+///
+/// Each macro optionally takes a `LaneOrder` type after the element type, e.g.
+/// `pack!(u16, IdentityOrder, 15, packed, lane, |$idx| ...)`, selecting how each lane's rows are
+/// laid out. It defaults to `FastLanesOrder`.
 
 #[macro_export]
 macro_rules! iterate {
     ($T:ty, $lane: expr, | $_1:tt $idx:ident | $($body:tt)*) => {
+        $crate::iterate!($T, $crate::FastLanesOrder, $lane, | $_1 $idx | $($body)*)
+    };
+    ($T:ty, $O:ty, $lane: expr, | $_1:tt $idx:ident | $($body:tt)*) => {
         macro_rules! __kernel__ {( $_1 $idx:ident ) => ( $($body)* )}
         {
-            use $crate::{seq_t, FL_ORDER};
+            use $crate::seq_t;
             use paste::paste;
 
-            #[inline(always)]
-            fn index(row: usize, lane: usize) -> usize {
-                let o = row / 8;
-                let s = row % 8;
-                (FL_ORDER[o] * 16) + (s * 128) + lane
-            }
-
             paste!(seq_t!(row in $T {
-                let idx = index(row, $lane);
+                let idx = $crate::ordered_index_of::<$T, $O>(row, $lane);
                 __kernel__!(idx);
             }));
         }
@@ -34,27 +34,23 @@ macro_rules! iterate {
 #[macro_export]
 macro_rules! pack {
     ($T:ty, $W:expr, $packed:expr, $lane:expr, | $_1:tt $idx:ident | $($body:tt)*) => {
+        $crate::pack!($T, $crate::FastLanesOrder, $W, $packed, $lane, | $_1 $idx | $($body)*)
+    };
+    ($T:ty, $O:ty, $W:expr, $packed:expr, $lane:expr, | $_1:tt $idx:ident | $($body:tt)*) => {
         macro_rules! __kernel__ {( $_1 $idx:ident ) => ( $($body)* )}
         {
-            use $crate::{seq_t, FL_ORDER};
+            use $crate::seq_t;
             use paste::paste;
 
             // The number of bits of T.
             const T: usize = <$T>::T;
 
-            #[inline(always)]
-            fn index(row: usize, lane: usize) -> usize {
-                let o = row / 8;
-                let s = row % 8;
-                (FL_ORDER[o] * 16) + (s * 128) + lane
-            }
-
             if $W == 0 {
                 // Nothing to do if W is 0, since the packed array is zero bytes.
             } else if $W == T {
                 // Special case for W=T, we can just copy the input value directly to the packed value.
                 paste!(seq_t!(row in $T {
-                    let idx = index(row, $lane);
+                    let idx = $crate::ordered_index_of::<$T, $O>(row, $lane);
                     $packed[<$T>::LANES * row + $lane] = __kernel__!(idx);
                 }));
             } else {
@@ -68,7 +64,7 @@ macro_rules! pack {
                 // Inlining this loop means all branches are known at compile time and
                 // the code is auto-vectorized for SIMD execution.
                 paste!(seq_t!(row in $T {
-                    let idx = index(row, $lane);
+                    let idx = $crate::ordered_index_of::<$T, $O>(row, $lane);
                     let src = __kernel__!(idx);
                     let src = src & mask;
 
@@ -100,33 +96,29 @@ macro_rules! pack {
 #[macro_export]
 macro_rules! unpack {
     ($T:ty, $W:expr, $packed:expr, $lane:expr, | $_1:tt $idx:ident, $_2:tt $elem:ident | $($body:tt)*) => {
+        $crate::unpack!($T, $crate::FastLanesOrder, $W, $packed, $lane, | $_1 $idx, $_2 $elem | $($body)*)
+    };
+    ($T:ty, $O:ty, $W:expr, $packed:expr, $lane:expr, | $_1:tt $idx:ident, $_2:tt $elem:ident | $($body:tt)*) => {
         macro_rules! __kernel__ {( $_1 $idx:ident, $_2 $elem:ident ) => ( $($body)* )}
         {
-            use $crate::{seq_t, FL_ORDER};
+            use $crate::seq_t;
             use paste::paste;
 
             // The number of bits of T.
             const T: usize = <$T>::T;
 
-            #[inline(always)]
-            fn index(row: usize, lane: usize) -> usize {
-                let o = row / 8;
-                let s = row % 8;
-                (FL_ORDER[o] * 16) + (s * 128) + lane
-            }
-
             if $W == 0 {
                 // Special case for W=0, we just need to zero the output.
                 // We'll still respect the iteration order in case the kernel has side effects.
                 paste!(seq_t!(row in $T {
-                    let idx = index(row, $lane);
+                    let idx = $crate::ordered_index_of::<$T, $O>(row, $lane);
                     let zero: $T = 0;
                     __kernel__!(idx, zero);
                 }));
             } else if $W == T {
                 // Special case for W=T, we can just copy the packed value directly to the output.
                 paste!(seq_t!(row in $T {
-                    let idx = index(row, $lane);
+                    let idx = $crate::ordered_index_of::<$T, $O>(row, $lane);
                     let src = $packed[<$T>::LANES * row + $lane];
                     __kernel__!(idx, src);
                 }));
@@ -165,7 +157,7 @@ macro_rules! unpack {
                     }
 
                     // Write out the unpacked value
-                    let idx = index(row, $lane);
+                    let idx = $crate::ordered_index_of::<$T, $O>(row, $lane);
                     __kernel__!(idx, tmp);
                 }));
             }
@@ -175,7 +167,7 @@ macro_rules! unpack {
 
 #[cfg(test)]
 mod test {
-    use crate::{BitPacking, FastLanes};
+    use crate::{BitPacking, FastLanes, IdentityOrder};
 
     #[test]
     fn test_pack() {
@@ -205,4 +197,33 @@ mod test {
 
         assert_eq!(values, unpacked);
     }
+
+    #[test]
+    fn test_pack_identity_order() {
+        let values: [u16; 1024] = core::array::from_fn(|i| (i % (1 << 15)) as u16);
+
+        let mut packed: [u16; 960] = [0; 960];
+        for lane in 0..u16::LANES {
+            pack!(u16, IdentityOrder, 15, packed, lane, |$pos| {
+                values[$pos]
+            });
+        }
+
+        let mut unpacked: [u16; 1024] = [0; 1024];
+        for lane in 0..u16::LANES {
+            unpack!(u16, IdentityOrder, 15, packed, lane, |$idx, $elem| {
+                unpacked[$idx] = $elem;
+            });
+        }
+        assert_eq!(values, unpacked);
+
+        // Under the identity order, packing at full width is a plain copy.
+        let mut copied: [u16; 1024] = [0; 1024];
+        for lane in 0..u16::LANES {
+            pack!(u16, IdentityOrder, 16, copied, lane, |$pos| {
+                values[$pos]
+            });
+        }
+        assert_eq!(values, copied);
+    }
 }
@@ -0,0 +1,518 @@
+#![allow(unused_assignments)]
+
+use arrayref::{array_mut_ref, array_ref};
+use core::mem::size_of;
+use paste::paste;
+
+use crate::{
+    iterate, pack, seq_t, transpose, unpack, BitPackWidth, Delta, FastLanes, FoR, FoRHeader,
+    SupportedBitPackWidth,
+};
+
+/// Returns whether element `index` is valid in a linear validity bitmap, where bit `index % 64` of
+/// word `index / 64` is set for each valid element.
+#[must_use]
+pub const fn validity_bit(validity: &[u64; 16], index: usize) -> bool {
+    validity[index / 64] & (1 << (index % 64)) != 0
+}
+
+/// Encodings of chunks with null elements, given alongside the values as a validity bitmap.
+///
+/// The values in null slots are ignored and replaced by whatever encodes cheapest, so the
+/// existing decoders apply as they are. The validity itself is packed one bit per element in the
+/// order the values are packed, so that word `lane` holds the validity of each row of that lane
+/// and the fused decoders read it alongside the packed values.
+pub trait Nullable: FoR + Delta {
+    /// Packs a linear validity bitmap one bit per element in `FastLanes` order, with bit `row` of
+    /// word `lane` holding the validity of element `index_of(row, lane)`.
+    fn validity_pack(validity: &[u64; 16], output: &mut [Self; Self::LANES]);
+
+    /// Unpacks a validity bitmap packed by [`Nullable::validity_pack`] back into linear order.
+    fn validity_unpack(input: &[Self; Self::LANES], output: &mut [u64; 16]);
+
+    /// Packs a linear validity bitmap for transposed values, with bit `row` of word `lane` holding
+    /// the validity of element `transpose(index_of(row, lane))`.
+    fn validity_pack_transposed(validity: &[u64; 16], output: &mut [Self; Self::LANES]);
+
+    /// Packs 1024 elements into `W` bits each like [`BitPacking::pack`], with null slots packed
+    /// as zero.
+    ///
+    /// [`BitPacking::pack`]: crate::BitPacking::pack
+    fn pack_nullable<const W: usize>(
+        input: &[Self; 1024],
+        validity: &[u64; 16],
+        output: &mut [Self; 1024 * W / Self::T],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Unpacks 1024 elements from `W` bits each, reading the validity packed by
+    /// [`Nullable::validity_pack`] in the same pass and writing `null` to every null slot.
+    fn unpack_nullable<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        validity: &[Self; Self::LANES],
+        null: Self,
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// FoR-encodes 1024 elements like [`FoR::for_pack_auto`], with the reference and width taken
+    /// from the valid elements only and null slots filled with the reference.
+    fn for_pack_nullable(
+        input: &[Self; 1024],
+        validity: &[u64; 16],
+        output: &mut [Self; 1024],
+    ) -> FoRHeader<Self>;
+
+    /// FoR-decodes 1024 elements from `W` bits each, reading the validity packed by
+    /// [`Nullable::validity_pack`] in the same pass and writing `null` to every null slot.
+    fn unfor_pack_nullable<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        validity: &[Self; Self::LANES],
+        reference: Self,
+        null: Self,
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// FoR-decodes 1024 elements like [`Nullable::unfor_pack_nullable`], where `W` is
+    /// runtime-known instead of compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of length `1024 * W / T`, where `T` is the bit-width of Self and `W`
+    /// is the packed width. The output slice must be of exactly length 1024.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_unfor_pack_nullable(
+        width: usize,
+        input: &[Self],
+        validity: &[Self; Self::LANES],
+        reference: Self,
+        null: Self,
+        output: &mut [Self],
+    );
+
+    /// Decodes 1024 elements encoded by [`Nullable::for_pack_nullable`] like
+    /// [`Nullable::unfor_pack_nullable`], where the width is taken from the header.
+    ///
+    /// # Panics
+    /// If the input is not exactly `header.packed_len()` words long.
+    fn unfor_pack_nullable_auto(
+        header: &FoRHeader<Self>,
+        input: &[Self],
+        validity: &[Self; Self::LANES],
+        null: Self,
+        output: &mut [Self; 1024],
+    );
+
+    /// Delta-encodes 1024 transposed elements like [`Delta::delta`], with each null slot filled
+    /// with the previous value in its lane so that its delta is zero.
+    ///
+    /// The validity bitmap is linear, i.e. it describes the elements before they were transposed.
+    fn delta_nullable(
+        input: &[Self; 1024],
+        validity: &[u64; 16],
+        base: &[Self; Self::LANES],
+        output: &mut [Self; 1024],
+    );
+
+    /// Unpacks and decodes deltas encoded by [`Nullable::delta_nullable`] like
+    /// [`Delta::undelta_pack`], reading the validity packed by
+    /// [`Nullable::validity_pack_transposed`] in the same pass and writing `null` to every null
+    /// slot.
+    fn undelta_pack_nullable<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        validity: &[Self; Self::LANES],
+        base: &[Self; Self::LANES],
+        null: Self,
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+}
+
+macro_rules! impl_nullable {
+    ($T:ty) => {
+        paste! {
+            impl Nullable for $T {
+                fn validity_pack(validity: &[u64; 16], output: &mut [Self; Self::LANES]) {
+                    for lane in 0..Self::LANES {
+                        pack!($T, 1, output, lane, |$idx| {
+                            <$T>::from(validity_bit(validity, $idx))
+                        });
+                    }
+                }
+
+                fn validity_unpack(input: &[Self; Self::LANES], output: &mut [u64; 16]) {
+                    *output = [0; 16];
+                    for lane in 0..Self::LANES {
+                        unpack!($T, 1, input, lane, |$idx, $elem| {
+                            output[$idx / 64] |= u64::from($elem) << ($idx % 64);
+                        });
+                    }
+                }
+
+                fn validity_pack_transposed(validity: &[u64; 16], output: &mut [Self; Self::LANES]) {
+                    for lane in 0..Self::LANES {
+                        pack!($T, 1, output, lane, |$idx| {
+                            <$T>::from(validity_bit(validity, transpose($idx)))
+                        });
+                    }
+                }
+
+                fn pack_nullable<const W: usize>(
+                    input: &[Self; 1024],
+                    validity: &[u64; 16],
+                    output: &mut [Self; 1024 * W / Self::T],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        pack!($T, W, output, lane, |$idx| {
+                            if validity_bit(validity, $idx) {
+                                input[$idx]
+                            } else {
+                                0
+                            }
+                        });
+                    }
+                }
+
+                fn unpack_nullable<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    validity: &[Self; Self::LANES],
+                    null: Self,
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        // The kernel visits the rows in order, so the validity of each row is the
+                        // lowest bit left in the lane's word.
+                        let mut valid = validity[lane];
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            output[$idx] = if valid & 1 == 1 { $elem } else { null };
+                            valid >>= 1;
+                        });
+                    }
+                }
+
+                fn for_pack_nullable(
+                    input: &[Self; 1024],
+                    validity: &[u64; 16],
+                    output: &mut [Self; 1024],
+                ) -> FoRHeader<Self> {
+                    // Filling the null slots with the smallest valid value leaves the header
+                    // computed over the filled chunk as it would be for the valid elements alone.
+                    let reference = (0..1024)
+                        .filter(|&i| validity_bit(validity, i))
+                        .map(|i| input[i])
+                        .min()
+                        .unwrap_or(0);
+                    let filled: [Self; 1024] = core::array::from_fn(|i| {
+                        if validity_bit(validity, i) {
+                            input[i]
+                        } else {
+                            reference
+                        }
+                    });
+                    Self::for_pack_auto(&filled, output)
+                }
+
+                fn unfor_pack_nullable<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    validity: &[Self; Self::LANES],
+                    reference: Self,
+                    null: Self,
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        let mut valid = validity[lane];
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            output[$idx] = if valid & 1 == 1 {
+                                $elem.wrapping_add(reference)
+                            } else {
+                                null
+                            };
+                            valid >>= 1;
+                        });
+                    }
+                }
+
+                unsafe fn unchecked_unfor_pack_nullable(
+                    width: usize,
+                    input: &[Self],
+                    validity: &[Self; Self::LANES],
+                    reference: Self,
+                    null: Self,
+                    output: &mut [Self],
+                ) {
+                    let packed_len = 128 * width / size_of::<Self>();
+                    debug_assert_eq!(input.len(), packed_len, "Input buffer must be of size 1024 * W / T");
+                    debug_assert_eq!(output.len(), 1024, "Output buffer must be of size 1024");
+                    debug_assert!(width <= Self::T, "Width must be less than or equal to {}", Self::T);
+
+                    seq_t!(W in $T {
+                        match width {
+                            #(W => Self::unfor_pack_nullable::<W>(
+                                array_ref![input, 0, 1024 * W / <$T>::T],
+                                validity,
+                                reference,
+                                null,
+                                array_mut_ref![output, 0, 1024],
+                            ),)*
+                            // seq_t has exclusive upper bound
+                            Self::T => Self::unfor_pack_nullable::<{ Self::T }>(
+                                array_ref![input, 0, 1024],
+                                validity,
+                                reference,
+                                null,
+                                array_mut_ref![output, 0, 1024],
+                            ),
+                            _ => unreachable!("Unsupported width: {}", width)
+                        }
+                    })
+                }
+
+                fn unfor_pack_nullable_auto(
+                    header: &FoRHeader<Self>,
+                    input: &[Self],
+                    validity: &[Self; Self::LANES],
+                    null: Self,
+                    output: &mut [Self; 1024],
+                ) {
+                    assert_eq!(input.len(), header.packed_len(), "Input must be the packed length of the header");
+                    unsafe {
+                        Self::unchecked_unfor_pack_nullable(header.width, input, validity, header.reference, null, output);
+                    }
+                }
+
+                fn delta_nullable(
+                    input: &[Self; 1024],
+                    validity: &[u64; 16],
+                    base: &[Self; Self::LANES],
+                    output: &mut [Self; 1024],
+                ) {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        iterate!($T, lane, |$idx| {
+                            let next = if validity_bit(validity, transpose($idx)) {
+                                input[$idx]
+                            } else {
+                                prev
+                            };
+                            output[$idx] = next.wrapping_sub(prev);
+                            prev = next;
+                        });
+                    }
+                }
+
+                fn undelta_pack_nullable<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    validity: &[Self; Self::LANES],
+                    base: &[Self; Self::LANES],
+                    null: Self,
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        // Null slots have a zero delta, so the running value passes through them.
+                        let mut prev = base[lane];
+                        let mut valid = validity[lane];
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            prev = $elem.wrapping_add(prev);
+                            output[$idx] = if valid & 1 == 1 { prev } else { null };
+                            valid >>= 1;
+                        });
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_nullable!(u8);
+impl_nullable!(u16);
+impl_nullable!(u32);
+impl_nullable!(u64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{index_of, untranspose, BitPacking, Transpose};
+
+    // Every third element is null.
+    fn validity() -> [u64; 16] {
+        let mut validity = [0; 16];
+        for i in (0..1024).filter(|i| i % 3 != 0) {
+            validity[i / 64] |= 1 << (i % 64);
+        }
+        validity
+    }
+
+    #[test]
+    fn test_validity_pack() {
+        let validity = validity();
+        let mut packed = [0u16; 64];
+        Nullable::validity_pack(&validity, &mut packed);
+        for lane in 0..64 {
+            for row in 0..16 {
+                let bit = packed[lane] >> row & 1 == 1;
+                assert_eq!(bit, validity_bit(&validity, index_of::<u16>(row, lane)));
+            }
+        }
+
+        let mut unpacked = [0; 16];
+        Nullable::validity_unpack(&packed, &mut unpacked);
+        assert_eq!(unpacked, validity);
+    }
+
+    #[test]
+    fn test_for_pack_nullable() {
+        let validity = validity();
+        // Garbage in the null slots would need the full width.
+        let values: [u32; 1024] = core::array::from_fn(|i| {
+            if i % 3 == 0 {
+                u32::MAX
+            } else {
+                1000 + i as u32 % 7
+            }
+        });
+
+        let mut packed = [0; 1024];
+        let header = Nullable::for_pack_nullable(&values, &validity, &mut packed);
+        assert_eq!(
+            header,
+            FoRHeader {
+                reference: 1000,
+                width: 3
+            }
+        );
+
+        let mut decoded = [0; 1024];
+        FoR::unfor_pack_auto(&header, &packed[..header.packed_len()], &mut decoded);
+        for i in 0..1024 {
+            let expected = if validity_bit(&validity, i) {
+                values[i]
+            } else {
+                1000
+            };
+            assert_eq!(decoded[i], expected);
+        }
+
+        // The fused decoder nulls out the slots the validity marks.
+        let mut packed_validity = [0; 32];
+        Nullable::validity_pack(&validity, &mut packed_validity);
+        let mut decoded = [1; 1024];
+        Nullable::unfor_pack_nullable_auto(
+            &header,
+            &packed[..header.packed_len()],
+            &packed_validity,
+            0,
+            &mut decoded,
+        );
+        for i in 0..1024 {
+            let expected = if validity_bit(&validity, i) {
+                values[i]
+            } else {
+                0
+            };
+            assert_eq!(decoded[i], expected);
+        }
+
+        let header = Nullable::for_pack_nullable(&values, &[0; 16], &mut packed);
+        assert_eq!(header.width, 0);
+    }
+
+    #[test]
+    fn test_delta_nullable() {
+        let validity = validity();
+        // Garbage in the null slots would break each lane's run of small deltas.
+        let values: [u16; 1024] =
+            core::array::from_fn(|i| if i % 3 == 0 { 0 } else { 5000 + i as u16 });
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+        let base = [5000; 64];
+
+        let mut deltas = [0; 1024];
+        Nullable::delta_nullable(&transposed, &validity, &base, &mut deltas);
+        for i in (0..1024).step_by(3) {
+            assert_eq!(deltas[untranspose(i)], 0);
+        }
+
+        let mut undelta = [0; 1024];
+        Delta::undelta(&deltas, &base, &mut undelta);
+        let mut decoded = [0; 1024];
+        Transpose::untranspose(&undelta, &mut decoded);
+        for i in (0..1024).filter(|&i| validity_bit(&validity, i)) {
+            assert_eq!(decoded[i], values[i]);
+        }
+
+        // The fused decoder nulls out the slots the transposed validity marks.
+        let mut packed = [0; 1024 * 11 / 16];
+        BitPacking::pack::<11>(&deltas, &mut packed);
+        let mut packed_validity = [0; 64];
+        Nullable::validity_pack_transposed(&validity, &mut packed_validity);
+        let mut undelta = [1; 1024];
+        Nullable::undelta_pack_nullable::<11>(&packed, &packed_validity, &base, 0, &mut undelta);
+        Transpose::untranspose(&undelta, &mut decoded);
+        for i in 0..1024 {
+            let expected = if validity_bit(&validity, i) {
+                values[i]
+            } else {
+                0
+            };
+            assert_eq!(decoded[i], expected);
+        }
+    }
+
+    #[test]
+    fn test_validity_pack_transposed() {
+        let validity = validity();
+        let mut packed = [0u32; 32];
+        Nullable::validity_pack_transposed(&validity, &mut packed);
+        for lane in 0..32 {
+            for row in 0..32 {
+                let bit = packed[lane] >> row & 1 == 1;
+                assert_eq!(
+                    bit,
+                    validity_bit(&validity, transpose(index_of::<u32>(row, lane)))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pack_nullable() {
+        let validity = validity();
+        // Garbage in the null slots would need the full width.
+        let values: [u8; 1024] =
+            core::array::from_fn(|i| if i % 3 == 0 { u8::MAX } else { i as u8 % 16 });
+
+        let mut packed = [0; 512];
+        Nullable::pack_nullable::<4>(&values, &validity, &mut packed);
+        let mut decoded = [0; 1024];
+        BitPacking::unpack::<4>(&packed, &mut decoded);
+        for i in 0..1024 {
+            let expected = if validity_bit(&validity, i) {
+                values[i]
+            } else {
+                0
+            };
+            assert_eq!(decoded[i], expected);
+        }
+
+        let mut packed_validity = [0; 128];
+        Nullable::validity_pack(&validity, &mut packed_validity);
+        let mut decoded = [0; 1024];
+        Nullable::unpack_nullable::<4>(&packed, &packed_validity, 42, &mut decoded);
+        for i in 0..1024 {
+            let expected = if validity_bit(&validity, i) {
+                values[i]
+            } else {
+                42
+            };
+            assert_eq!(decoded[i], expected);
+        }
+    }
+}
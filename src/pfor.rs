@@ -0,0 +1,335 @@
+use alloc::vec::Vec;
+use arrayref::{array_mut_ref, array_ref};
+use core::mem::size_of;
+use paste::paste;
+
+use crate::{seq_t, unpack, BitPackWidth, FastLanes, FoR, FoRHeader, SupportedBitPackWidth};
+
+/// Exceptions to an encoding, stored as (index, value) pairs in index order and patched over
+/// the decoded chunk.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Patches<T> {
+    pub indices: Vec<u16>,
+    pub values: Vec<T>,
+}
+
+impl<T: Copy> Patches<T> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            indices: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, index: usize, value: T) {
+        self.indices.push(index as u16);
+        self.values.push(value);
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Overwrites each patched index of the output with its exception value.
+    pub fn apply(&self, output: &mut [T; 1024]) {
+        for (&index, &value) in self.indices.iter().zip(self.values.iter()) {
+            output[index as usize] = value;
+        }
+    }
+}
+
+/// Patched Frame-of-Reference (`PFoR`).
+///
+/// Packs a chunk into a width that covers most of its values, storing the few values that do not
+/// fit, above or below the reference, as [`Patches`]. A single outlier no longer forces the whole
+/// chunk to a wider width.
+pub trait PatchedFoR: FoR {
+    /// Chooses the reference and width minimising the packed size plus the size of the
+    /// exceptions, where each exception costs a `u16` index and a full `T` value.
+    ///
+    /// The values that fit are the widest window of the sorted chunk spanning fewer than
+    /// `2^width` values, so outliers on either side of it become exceptions.
+    fn pfor_header(input: &[Self; 1024]) -> FoRHeader<Self>;
+
+    /// Patched-FoR-encodes 1024 elements, choosing the reference and width with
+    /// [`PatchedFoR::pfor_header`].
+    fn pfor_pack(
+        input: &[Self; 1024],
+        output: &mut [Self; 1024],
+    ) -> (FoRHeader<Self>, Patches<Self>);
+
+    /// Unpacks 1024 `W` bit elements and adds the reference, writing the exception value instead
+    /// at each patched index in the same pass.
+    fn unpfor_pack<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        reference: Self,
+        patches: &Patches<Self>,
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+
+    /// Decodes 1024 patched elements from `W` bits each, where `W` is runtime-known instead of
+    /// compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of length `1024 * W / T`, where `T` is the bit-width of Self and `W`
+    /// is the packed width. The output slice must be of exactly length 1024.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_unpfor_pack(
+        width: usize,
+        input: &[Self],
+        reference: Self,
+        patches: &Patches<Self>,
+        output: &mut [Self],
+    );
+
+    /// Decodes 1024 elements encoded by [`PatchedFoR::pfor_pack`].
+    ///
+    /// # Panics
+    /// If the input is not exactly `header.packed_len()` words long.
+    fn unpfor_pack_auto(
+        header: &FoRHeader<Self>,
+        patches: &Patches<Self>,
+        input: &[Self],
+        output: &mut [Self; 1024],
+    );
+}
+
+macro_rules! impl_pfor {
+    ($T:ty) => {
+        paste! {
+            impl PatchedFoR for $T {
+                fn pfor_header(input: &[Self; 1024]) -> FoRHeader<Self> {
+                    let mut sorted = *input;
+                    sorted.sort_unstable();
+                    let bits = |value: Self| Self::T - value.leading_zeros() as usize;
+
+                    // At full width every value fits, without any exceptions.
+                    let exception_bits = 16 + Self::T;
+                    let mut best = FoRHeader { reference: sorted[0], width: Self::T };
+                    let mut best_cost = 1024 * Self::T;
+                    for width in 0..Self::T {
+                        // Slide a window over the sorted values, keeping the widest one whose
+                        // span fits in `width` bits.
+                        let (mut lo, mut best_lo, mut best_len) = (0, 0, 0);
+                        for hi in 0..1024 {
+                            while bits(sorted[hi] - sorted[lo]) > width {
+                                lo += 1;
+                            }
+                            if hi + 1 - lo > best_len {
+                                (best_lo, best_len) = (lo, hi + 1 - lo);
+                            }
+                        }
+
+                        let cost = 1024 * width + (1024 - best_len) * exception_bits;
+                        if cost < best_cost {
+                            best = FoRHeader { reference: sorted[best_lo], width };
+                            best_cost = cost;
+                        }
+                    }
+                    best
+                }
+
+                fn pfor_pack(
+                    input: &[Self; 1024],
+                    output: &mut [Self; 1024],
+                ) -> (FoRHeader<Self>, Patches<Self>) {
+                    let header = Self::pfor_header(input);
+                    let reference = header.reference;
+
+                    let mut patches = Patches::new();
+                    for (i, &value) in input.iter().enumerate() {
+                        if value < reference
+                            || (Self::T - (value - reference).leading_zeros() as usize) > header.width
+                        {
+                            patches.push(i, value);
+                        }
+                    }
+
+                    // Packing keeps only the low bits of each exception, which the patches then
+                    // overwrite.
+                    unsafe {
+                        Self::unchecked_for_pack(
+                            header.width,
+                            input,
+                            reference,
+                            &mut output[..header.packed_len()],
+                        );
+                    }
+                    (header, patches)
+                }
+
+                fn unpfor_pack<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    reference: Self,
+                    patches: &Patches<Self>,
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    // A bitmap of the patched indices, along with the number of patches before each
+                    // of its words, gives the rank of every patch in the unpacked order.
+                    let mut exceptions = [0u64; 16];
+                    for &index in &patches.indices {
+                        exceptions[index as usize / 64] |= 1 << (index % 64);
+                    }
+                    let mut ranks = [0usize; 16];
+                    for word in 1..16 {
+                        ranks[word] = ranks[word - 1] + exceptions[word - 1].count_ones() as usize;
+                    }
+
+                    for lane in 0..Self::LANES {
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            let word = exceptions[$idx / 64];
+                            let bit = $idx % 64;
+                            output[$idx] = if (word >> bit) & 1 == 0 {
+                                $elem.wrapping_add(reference)
+                            } else {
+                                let below = (word & ((1 << bit) - 1)).count_ones() as usize;
+                                patches.values[ranks[$idx / 64] + below]
+                            };
+                        });
+                    }
+                }
+
+                unsafe fn unchecked_unpfor_pack(
+                    width: usize,
+                    input: &[Self],
+                    reference: Self,
+                    patches: &Patches<Self>,
+                    output: &mut [Self],
+                ) {
+                    let packed_len = 128 * width / size_of::<Self>();
+                    debug_assert_eq!(input.len(), packed_len, "Input buffer must be of size 1024 * W / T");
+                    debug_assert_eq!(output.len(), 1024, "Output buffer must be of size 1024");
+                    debug_assert!(width <= Self::T, "Width must be less than or equal to {}", Self::T);
+
+                    seq_t!(W in $T {
+                        match width {
+                            #(W => Self::unpfor_pack::<W>(
+                                array_ref![input, 0, 1024 * W / <$T>::T],
+                                reference,
+                                patches,
+                                array_mut_ref![output, 0, 1024],
+                            ),)*
+                            // seq_t has exclusive upper bound
+                            Self::T => Self::unpfor_pack::<{ Self::T }>(
+                                array_ref![input, 0, 1024],
+                                reference,
+                                patches,
+                                array_mut_ref![output, 0, 1024],
+                            ),
+                            _ => unreachable!("Unsupported width: {}", width)
+                        }
+                    })
+                }
+
+                fn unpfor_pack_auto(
+                    header: &FoRHeader<Self>,
+                    patches: &Patches<Self>,
+                    input: &[Self],
+                    output: &mut [Self; 1024],
+                ) {
+                    assert_eq!(input.len(), header.packed_len(), "Input must be the packed length of the header");
+                    unsafe { Self::unchecked_unpfor_pack(header.width, input, header.reference, patches, output) };
+                }
+            }
+        }
+    };
+}
+
+impl_pfor!(u8);
+impl_pfor!(u16);
+impl_pfor!(u32);
+impl_pfor!(u64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pfor_outliers() {
+        let mut values: [u32; 1024] = core::array::from_fn(|i| 100 + (i % 16) as u32);
+        values[17] = u32::MAX;
+        values[900] = 1 << 20;
+
+        let mut packed = [0; 1024];
+        let (header, patches) = PatchedFoR::pfor_pack(&values, &mut packed);
+        assert_eq!(header.reference, 100);
+        assert_eq!(header.width, 4);
+        assert_eq!(patches.indices, [17, 900]);
+        assert_eq!(patches.values, [u32::MAX, 1 << 20]);
+
+        let mut unpacked = [0; 1024];
+        PatchedFoR::unpfor_pack_auto(
+            &header,
+            &patches,
+            &packed[..header.packed_len()],
+            &mut unpacked,
+        );
+        assert_eq!(values, unpacked);
+
+        let mut unpacked = [0; 1024];
+        PatchedFoR::unpfor_pack::<4>(
+            array_ref![packed, 0, 128],
+            header.reference,
+            &patches,
+            &mut unpacked,
+        );
+        assert_eq!(values, unpacked);
+    }
+
+    #[test]
+    fn test_pfor_no_exceptions() {
+        let values: [u16; 1024] = core::array::from_fn(|i| (i * 31) as u16);
+
+        let mut packed = [0; 1024];
+        let (header, patches) = PatchedFoR::pfor_pack(&values, &mut packed);
+        assert_eq!(header, FoRHeader::compute(&values));
+        assert!(patches.is_empty());
+
+        let mut unpacked = [0; 1024];
+        PatchedFoR::unpfor_pack_auto(
+            &header,
+            &patches,
+            &packed[..header.packed_len()],
+            &mut unpacked,
+        );
+        assert_eq!(values, unpacked);
+    }
+
+    #[test]
+    fn test_pfor_low_outlier() {
+        let mut values: [u32; 1024] = core::array::from_fn(|i| (1 << 30) + (i % 16) as u32);
+        values[5] = 0;
+        values[6] = 1 << 31;
+
+        let mut packed = [0; 1024];
+        let (header, patches) = PatchedFoR::pfor_pack(&values, &mut packed);
+        assert_eq!(
+            header,
+            FoRHeader {
+                reference: 1 << 30,
+                width: 4
+            }
+        );
+        assert_eq!(patches.indices, [5, 6]);
+        assert_eq!(patches.values, [0, 1 << 31]);
+
+        let mut unpacked = [0; 1024];
+        PatchedFoR::unpfor_pack_auto(
+            &header,
+            &patches,
+            &packed[..header.packed_len()],
+            &mut unpacked,
+        );
+        assert_eq!(values, unpacked);
+    }
+}
@@ -0,0 +1,233 @@
+#![allow(unused_assignments)]
+
+use alloc::vec::Vec;
+use arrayref::{array_mut_ref, array_ref};
+use core::mem::size_of;
+
+use crate::{
+    index_of, seq_t, transpose, unpack, BitEq, BitPackWidth, BitPacking, Constant, Delta,
+    FastLanes, SupportedBitPackWidth, Transpose,
+};
+
+/// The run values and index bases of a run-length encoded chunk.
+///
+/// Each element's run index is transposed, delta-encoded from `bases` and packed into `width`
+/// bits. Since every lane holds consecutive elements, the deltas are 0 or 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Runs<T> {
+    /// The value of each run, in order.
+    pub values: Vec<T>,
+    /// The run index of the first row of each lane.
+    pub bases: [u16; 64],
+    pub width: usize,
+}
+
+impl<T> Runs<T> {
+    /// The number of `u16` words the packed run index occupies.
+    #[must_use]
+    pub fn packed_len(&self) -> usize {
+        128 * self.width / size_of::<u16>()
+    }
+
+    /// Returns the constant if the chunk is a single run.
+    #[must_use]
+    pub fn constant(&self) -> Option<Constant<T>>
+    where
+        T: Copy,
+    {
+        match self.values.as_slice() {
+            &[value] => Some(Constant::Value(value)),
+            _ => None,
+        }
+    }
+}
+
+/// Run-length encoding in the transposed layout.
+///
+/// The decoder fuses undelta of the packed run index with the run value lookup, writing each
+/// value straight to its untransposed position. Runs are split by [`BitEq`], so floats decode to
+/// the exact bits they were encoded from.
+pub trait RunLength: BitEq {
+    /// Run-length encodes 1024 elements, packing the run index of each element.
+    fn rle_pack(input: &[Self; 1024], output: &mut [u16; 1024]) -> Runs<Self>;
+
+    /// Unpacks a `W` bit delta-encoded run index, writing the value of each run.
+    fn unrle_pack<const W: usize>(
+        input: &[u16; 1024 * W / u16::T],
+        bases: &[u16; 64],
+        values: &[Self],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<u16>;
+
+    /// Unpacks a `W` bit delta-encoded run index, writing the value of each run, where `W` is
+    /// runtime-known instead of compile-time known.
+    ///
+    /// # Safety
+    /// The input slice must be of length `1024 * W / 16`, where `W` is the packed width. The
+    /// output slice must be of exactly length 1024.
+    /// These lengths are checked only with `debug_assert` (i.e., not checked on release builds).
+    unsafe fn unchecked_unrle_pack(
+        width: usize,
+        input: &[u16],
+        bases: &[u16; 64],
+        values: &[Self],
+        output: &mut [Self],
+    );
+
+    /// Decodes 1024 elements encoded by [`RunLength::rle_pack`].
+    ///
+    /// # Panics
+    /// If the input is not exactly `runs.packed_len()` words long.
+    fn unrle_pack_auto(runs: &Runs<Self>, input: &[u16], output: &mut [Self; 1024]);
+}
+
+impl<T: BitEq> RunLength for T {
+    fn rle_pack(input: &[Self; 1024], output: &mut [u16; 1024]) -> Runs<Self> {
+        let mut values = Vec::new();
+        let mut runs = [0u16; 1024];
+        for i in 0..1024 {
+            if i == 0 || !input[i].bit_eq(input[i - 1]) {
+                values.push(input[i]);
+            }
+            runs[i] = (values.len() - 1) as u16;
+        }
+
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&runs, &mut transposed);
+
+        let bases: [u16; 64] = core::array::from_fn(|lane| transposed[index_of::<u16>(0, lane)]);
+        let mut deltas = [0; 1024];
+        Delta::delta(&transposed, &bases, &mut deltas);
+
+        let width = 16 - deltas.iter().fold(0, |acc, &d| acc | d).leading_zeros() as usize;
+        let runs = Runs {
+            values,
+            bases,
+            width,
+        };
+        let packed_len = runs.packed_len();
+        unsafe { BitPacking::unchecked_pack(width, &deltas, &mut output[..packed_len]) };
+        runs
+    }
+
+    fn unrle_pack<const W: usize>(
+        input: &[u16; 1024 * W / u16::T],
+        bases: &[u16; 64],
+        values: &[Self],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<u16>,
+    {
+        for lane in 0..u16::LANES {
+            let mut prev = bases[lane];
+            unpack!(u16, W, input, lane, |$idx, $elem| {
+                let next = $elem.wrapping_add(prev);
+                output[transpose($idx)] = values[next as usize];
+                prev = next;
+            });
+        }
+    }
+
+    // The packed length is `1024 * 0 / 16` for the zero-width arm.
+    #[allow(clippy::erasing_op, clippy::identity_op)]
+    unsafe fn unchecked_unrle_pack(
+        width: usize,
+        input: &[u16],
+        bases: &[u16; 64],
+        values: &[Self],
+        output: &mut [Self],
+    ) {
+        let packed_len = 128 * width / size_of::<u16>();
+        debug_assert_eq!(
+            input.len(),
+            packed_len,
+            "Input buffer must be of size 1024 * W / 16"
+        );
+        debug_assert_eq!(output.len(), 1024, "Output buffer must be of size 1024");
+        debug_assert!(width <= 16, "Width must be less than or equal to 16");
+
+        seq_t!(W in u16 {
+            match width {
+                #(W => Self::unrle_pack::<W>(
+                    array_ref![input, 0, 1024 * W / u16::T],
+                    bases,
+                    values,
+                    array_mut_ref![output, 0, 1024],
+                ),)*
+                // seq_t has exclusive upper bound
+                16 => Self::unrle_pack::<16>(
+                    array_ref![input, 0, 1024],
+                    bases,
+                    values,
+                    array_mut_ref![output, 0, 1024],
+                ),
+                _ => unreachable!("Unsupported width: {}", width)
+            }
+        });
+    }
+
+    fn unrle_pack_auto(runs: &Runs<Self>, input: &[u16], output: &mut [Self; 1024]) {
+        assert_eq!(
+            input.len(),
+            runs.packed_len(),
+            "Input must be the packed length of the runs"
+        );
+        unsafe { Self::unchecked_unrle_pack(runs.width, input, &runs.bases, &runs.values, output) };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip<T: RunLength + PartialEq + core::fmt::Debug>(values: &[T; 1024]) -> Runs<T> {
+        let mut packed = [0; 1024];
+        let runs = RunLength::rle_pack(values, &mut packed);
+
+        let mut decoded = [values[0]; 1024];
+        RunLength::unrle_pack_auto(&runs, &packed[..runs.packed_len()], &mut decoded);
+        assert_eq!(&decoded, values);
+        runs
+    }
+
+    #[test]
+    fn test_rle() {
+        let values: [u32; 1024] = core::array::from_fn(|i| (i / 100) as u32 * 7);
+        let runs = round_trip(&values);
+        assert_eq!(runs.values, (0..11).map(|r| r * 7).collect::<Vec<_>>());
+        assert_eq!(runs.width, 1);
+
+        // A single run packs its index into zero bits.
+        let runs = round_trip(&[1.5f64; 1024]);
+        assert_eq!(runs.values, [1.5]);
+        assert_eq!(runs.width, 0);
+
+        // Every element is its own run.
+        let values: [u16; 1024] = core::array::from_fn(|i| i as u16);
+        assert_eq!(round_trip(&values).values.len(), 1024);
+    }
+
+    #[test]
+    fn test_rle_signed_zero() {
+        // Signed zeros compare equal, but must still be separate runs.
+        let values: [f64; 1024] = core::array::from_fn(|i| if i < 512 { 0.0 } else { -0.0 });
+        let mut packed = [0; 1024];
+        let runs = RunLength::rle_pack(&values, &mut packed);
+        assert_eq!(runs.values.len(), 2);
+
+        let mut decoded = [1.0; 1024];
+        RunLength::unrle_pack_auto(&runs, &packed[..runs.packed_len()], &mut decoded);
+        assert_eq!(decoded.map(f64::to_bits), values.map(f64::to_bits));
+
+        // A NaN never compares equal, but repeated NaNs are a single run.
+        let values = [f32::NAN; 1024];
+        let runs = RunLength::rle_pack(&values, &mut packed);
+        assert_eq!(runs.values.len(), 1);
+        assert_eq!(runs.width, 0);
+
+        let mut decoded = [0.0; 1024];
+        RunLength::unrle_pack_auto(&runs, &packed[..runs.packed_len()], &mut decoded);
+        assert_eq!(decoded.map(f32::to_bits), values.map(f32::to_bits));
+    }
+}
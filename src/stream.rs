@@ -0,0 +1,284 @@
+use arrayref::{array_mut_ref, array_ref};
+
+use crate::{
+    index_of, BitPackWidth, BitPacking, Delta, FastLanes, SupportedBitPackWidth, Transpose,
+};
+
+/// Encodes a stream of transposed 1024-element chunks, carrying the last value of each lane
+/// over as the base of the next chunk.
+pub struct DeltaEncoder<T: Delta>
+where
+    [(); T::LANES]:,
+{
+    base: [T; T::LANES],
+}
+
+impl<T: Delta> DeltaEncoder<T>
+where
+    [(); T::LANES]:,
+{
+    /// Creates an encoder whose first chunk is delta-encoded against `base`.
+    pub fn new(base: [T; T::LANES]) -> Self {
+        Self { base }
+    }
+
+    /// The bases the next chunk will be encoded against.
+    ///
+    /// Store these per chunk to allow a [`DeltaDecoder`] to later seek to that chunk.
+    pub fn checkpoint(&self) -> &[T; T::LANES] {
+        &self.base
+    }
+
+    /// Delta-encodes a single transposed chunk and advances the bases.
+    pub fn encode(&mut self, input: &[T; 1024], output: &mut [T; 1024]) {
+        Delta::delta(input, &self.base, output);
+        last_row(input, &mut self.base);
+    }
+
+    /// Delta-encodes a single transposed chunk, packs the deltas into `W` bits each and advances
+    /// the bases.
+    ///
+    /// Every delta must fit in `W` bits.
+    pub fn encode_pack<const W: usize>(
+        &mut self,
+        input: &[T; 1024],
+        output: &mut [T; 1024 * W / T::T],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<T>,
+    {
+        let mut deltas = [T::zero(); 1024];
+        self.encode(input, &mut deltas);
+        BitPacking::pack::<W>(&deltas, output);
+    }
+
+    /// Transposes and delta-encodes a sequence of values in linear order, one chunk of 1024 at a
+    /// time. The last chunk may be partial.
+    ///
+    /// A partial final chunk is padded with its last value before it is transposed, so the output
+    /// holds a whole number of chunks. Decode it with [`DeltaDecoder::decode_all`] into an output
+    /// of the input's length.
+    ///
+    /// # Panics
+    /// If the output length is not the input length rounded up to a multiple of 1024.
+    pub fn encode_all(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(
+            output.len(),
+            input.len().div_ceil(1024) * 1024,
+            "Output length must be the input length rounded up to a multiple of 1024"
+        );
+        let mut transposed = [T::zero(); 1024];
+        for (input, output) in input.chunks(1024).zip(output.chunks_exact_mut(1024)) {
+            match input.try_into() {
+                Ok(input) => Transpose::transpose(input, &mut transposed),
+                Err(_) => Transpose::transpose(&pad(input), &mut transposed),
+            }
+            self.encode(&transposed, array_mut_ref![output, 0, 1024]);
+        }
+    }
+}
+
+impl<T: Delta> Default for DeltaEncoder<T>
+where
+    [(); T::LANES]:,
+{
+    fn default() -> Self {
+        Self::new([T::zero(); T::LANES])
+    }
+}
+
+/// Decodes a stream of chunks produced by a [`DeltaEncoder`].
+pub struct DeltaDecoder<T: Delta>
+where
+    [(); T::LANES]:,
+{
+    base: [T; T::LANES],
+}
+
+impl<T: Delta> DeltaDecoder<T>
+where
+    [(); T::LANES]:,
+{
+    /// Creates a decoder whose first chunk is decoded against `base`.
+    pub fn new(base: [T; T::LANES]) -> Self {
+        Self { base }
+    }
+
+    /// The bases the next chunk will be decoded against.
+    pub fn checkpoint(&self) -> &[T; T::LANES] {
+        &self.base
+    }
+
+    /// Positions the decoder at a chunk boundary, given the checkpoint the encoder reported
+    /// for that chunk.
+    pub fn seek(&mut self, checkpoint: &[T; T::LANES]) {
+        self.base = *checkpoint;
+    }
+
+    /// Decodes a single chunk of deltas and advances the bases.
+    pub fn decode(&mut self, input: &[T; 1024], output: &mut [T; 1024]) {
+        Delta::undelta(input, &self.base, output);
+        last_row(output, &mut self.base);
+    }
+
+    /// Decodes a single chunk of `W`-bit packed deltas and advances the bases.
+    pub fn decode_pack<const W: usize>(
+        &mut self,
+        input: &[T; 1024 * W / T::T],
+        output: &mut [T; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<T>,
+    {
+        Delta::undelta_pack::<W>(input, &self.base, output);
+        last_row(output, &mut self.base);
+    }
+
+    /// Decodes a sequence of delta chunks produced by [`DeltaEncoder::encode_all`], untransposing
+    /// each chunk back into linear order.
+    ///
+    /// The output may end in a partial chunk, which receives the leading values of the final
+    /// decoded chunk.
+    ///
+    /// # Panics
+    /// If the input length is not the output length rounded up to a multiple of 1024.
+    pub fn decode_all(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(
+            input.len(),
+            output.len().div_ceil(1024) * 1024,
+            "Input length must be the output length rounded up to a multiple of 1024"
+        );
+        let mut transposed = [T::zero(); 1024];
+        for (input, output) in input.chunks_exact(1024).zip(output.chunks_mut(1024)) {
+            self.decode(array_ref![input, 0, 1024], &mut transposed);
+            if let Ok(output) = output.try_into() {
+                Transpose::untranspose(&transposed, output);
+            } else {
+                let mut chunk = [T::zero(); 1024];
+                Transpose::untranspose(&transposed, &mut chunk);
+                output.copy_from_slice(&chunk[..output.len()]);
+            }
+        }
+    }
+}
+
+impl<T: Delta> Default for DeltaDecoder<T>
+where
+    [(); T::LANES]:,
+{
+    fn default() -> Self {
+        Self::new([T::zero(); T::LANES])
+    }
+}
+
+// Pads a partial chunk of linear values to 1024 elements by repeating its last value.
+fn pad<T: Copy>(chunk: &[T]) -> [T; 1024] {
+    core::array::from_fn(|i| chunk[i.min(chunk.len() - 1)])
+}
+
+// Copies the last row of each lane of a transposed chunk into `base`.
+fn last_row<T: FastLanes>(chunk: &[T; 1024], base: &mut [T; T::LANES]) {
+    for lane in 0..T::LANES {
+        base[lane] = chunk[index_of::<T>(T::T - 1, lane)];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_delta_stream() {
+        const W: usize = 12;
+        let values: [u32; 4096] = core::array::from_fn(|i| (1000 + i * 3) as u32);
+
+        // Transpose each chunk independently.
+        let mut transposed = [0u32; 4096];
+        for (input, output) in values
+            .chunks_exact(1024)
+            .zip(transposed.chunks_exact_mut(1024))
+        {
+            Transpose::transpose(array_ref![input, 0, 1024], array_mut_ref![output, 0, 1024]);
+        }
+
+        let mut encoder = DeltaEncoder::<u32>::new([1000; 32]);
+        let mut checkpoints = [[0u32; 32]; 4];
+        let mut deltas = [0u32; 4096];
+        for (i, (input, output)) in transposed
+            .chunks_exact(1024)
+            .zip(deltas.chunks_exact_mut(1024))
+            .enumerate()
+        {
+            checkpoints[i] = *encoder.checkpoint();
+            encoder.encode(array_ref![input, 0, 1024], array_mut_ref![output, 0, 1024]);
+        }
+
+        // The same deltas from the linear values in one call.
+        let mut all = [0u32; 4096];
+        DeltaEncoder::new([1000; 32]).encode_all(&values, &mut all);
+        assert_eq!(all, deltas);
+
+        let mut linear = [0u32; 4096];
+        DeltaDecoder::new([1000; 32]).decode_all(&deltas, &mut linear);
+        assert_eq!(values, linear);
+
+        // Seek straight to the third chunk and decode it from its packed deltas.
+        let mut packed = [0u32; 1024 * W / 32];
+        BitPacking::pack::<W>(array_ref![deltas, 2048, 1024], &mut packed);
+
+        let mut decoder = DeltaDecoder::<u32>::default();
+        decoder.seek(&checkpoints[2]);
+        let mut chunk = [0u32; 1024];
+        decoder.decode_pack::<W>(&packed, &mut chunk);
+        assert_eq!(&chunk, array_ref![transposed, 2048, 1024]);
+        assert_eq!(decoder.checkpoint(), &checkpoints[3]);
+    }
+
+    #[test]
+    fn test_delta_stream_partial() {
+        // Two and a half chunks of linear values.
+        let values: [u16; 2560] = core::array::from_fn(|i| (i * 7) as u16);
+        let mut deltas = [0u16; 3072];
+        DeltaEncoder::<u16>::default().encode_all(&values, &mut deltas);
+
+        // Each chunk is transposed, and the partial one is padded with its last value first.
+        let mut encoder = DeltaEncoder::<u16>::default();
+        for (i, chunk) in values.chunks(1024).enumerate() {
+            let padded: [u16; 1024] = core::array::from_fn(|j| chunk[j.min(chunk.len() - 1)]);
+            let mut transposed = [0; 1024];
+            Transpose::transpose(&padded, &mut transposed);
+            let mut expected = [0; 1024];
+            encoder.encode(&transposed, &mut expected);
+            assert_eq!(array_ref![deltas, i * 1024, 1024], &expected);
+        }
+
+        let mut decoded = [0u16; 2560];
+        DeltaDecoder::<u16>::default().decode_all(&deltas, &mut decoded);
+        assert_eq!(decoded, values);
+
+        let mut deltas = [0u16; 1024];
+        DeltaEncoder::<u16>::default().encode_all(&values[..10], &mut deltas);
+        let mut decoded = [0u16; 10];
+        DeltaDecoder::<u16>::default().decode_all(&deltas, &mut decoded);
+        assert_eq!(decoded, values[..10]);
+    }
+
+    #[test]
+    fn test_delta_stream_pack() {
+        const W: usize = 12;
+        let values: [u32; 2048] = core::array::from_fn(|i| (i * 3) as u32);
+
+        let mut encoder = DeltaEncoder::<u32>::default();
+        let mut decoder = DeltaDecoder::<u32>::default();
+        for chunk in values.chunks_exact(1024) {
+            let chunk = array_ref![chunk, 0, 1024];
+            let mut transposed = [0; 1024];
+            Transpose::transpose(chunk, &mut transposed);
+
+            let mut packed = [0; 1024 * W / 32];
+            encoder.encode_pack::<W>(&transposed, &mut packed);
+            let mut undelta = [0; 1024];
+            decoder.decode_pack::<W>(&packed, &mut undelta);
+            assert_eq!(undelta, transposed);
+        }
+        assert_eq!(encoder.checkpoint(), decoder.checkpoint());
+    }
+}
@@ -0,0 +1,143 @@
+#![allow(unused_assignments)]
+
+use crate::{iterate, unpack, BitPackWidth, BitPacking, FastLanes, SupportedBitPackWidth};
+use paste::paste;
+
+/// XOR-based delta encoding over the transposed layout.
+///
+/// Each value is XOR-ed with the previous value in its lane. Bit patterns that share their
+/// high-order bits, such as neighbouring floats cast to `u32`/`u64`, produce deltas with many
+/// leading zeros, which then pack into `T - leading_zeros` bits.
+pub trait XorDelta: BitPacking {
+    fn xor(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]);
+
+    fn unxor(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]);
+
+    fn unxor_pack<const W: usize>(
+        input: &[Self; 1024 * W / Self::T],
+        base: &[Self; Self::LANES],
+        output: &mut [Self; 1024],
+    ) where
+        BitPackWidth<W>: SupportedBitPackWidth<Self>;
+}
+
+macro_rules! impl_xor_delta {
+    ($T:ty) => {
+        paste! {
+            impl XorDelta for $T {
+                #[inline(never)]
+                fn xor(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]) {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        iterate!($T, lane, |$idx| {
+                            let next = input[$idx];
+                            output[$idx] = next ^ prev;
+                            prev = next;
+                        });
+                    }
+                }
+
+                #[inline(never)]
+                fn unxor(input: &[Self; 1024], base: &[Self; Self::LANES], output: &mut [Self; 1024]) {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        iterate!($T, lane, |$idx| {
+                            let next = input[$idx] ^ prev;
+                            output[$idx] = next;
+                            prev = next;
+                        });
+                    }
+                }
+
+                #[inline(never)]
+                fn unxor_pack<const W: usize>(
+                    input: &[Self; 1024 * W / Self::T],
+                    base: &[Self; Self::LANES],
+                    output: &mut [Self; 1024],
+                ) where
+                    BitPackWidth<W>: SupportedBitPackWidth<Self>,
+                {
+                    for lane in 0..Self::LANES {
+                        let mut prev = base[lane];
+                        unpack!($T, W, input, lane, |$idx, $elem| {
+                            let next = $elem ^ prev;
+                            output[$idx] = next;
+                            prev = next;
+                        });
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_xor_delta!(u8);
+impl_xor_delta!(u16);
+impl_xor_delta!(u32);
+impl_xor_delta!(u64);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Transpose;
+    use core::mem::size_of;
+
+    #[test]
+    fn test_xor_f64() {
+        const W: usize = 52;
+        // Doubles in [1024, 2048) share their sign and exponent bits.
+        let floats: [f64; 1024] = core::array::from_fn(|i| 1024.0 + f64::from(i as u32) * 0.75);
+        let values = floats.map(f64::to_bits);
+
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+
+        let base = [transposed[0]; 16];
+        let mut xored = [0; 1024];
+        XorDelta::xor(&transposed, &base, &mut xored);
+
+        // Leading-zero-aware width.
+        let width = 64 - xored.iter().fold(0, |acc, &x| acc | x).leading_zeros() as usize;
+        assert!(width <= W);
+
+        let mut packed = [0; 128 * W / size_of::<u64>()];
+        BitPacking::pack::<W>(&xored, &mut packed);
+
+        // Fused kernel
+        let mut unpacked = [0; 1024];
+        XorDelta::unxor_pack::<W>(&packed, &base, &mut unpacked);
+        assert_eq!(transposed, unpacked);
+
+        // Unfused kernel
+        let mut unxored = [0; 1024];
+        XorDelta::unxor(&xored, &base, &mut unxored);
+        assert_eq!(transposed, unxored);
+
+        let mut untransposed = [0; 1024];
+        Transpose::untranspose(&unpacked, &mut untransposed);
+        assert_eq!(floats.map(f64::to_bits), untransposed);
+    }
+
+    #[test]
+    fn test_xor_f32() {
+        const W: usize = 23;
+        // Floats in [2, 4) differ only in their mantissa.
+        let floats: [f32; 1024] = core::array::from_fn(|i| 3.0 + f32::from((i % 8) as u8) * 0.125);
+        let values = floats.map(f32::to_bits);
+
+        let mut transposed = [0; 1024];
+        Transpose::transpose(&values, &mut transposed);
+
+        let base = [3.0f32.to_bits(); 32];
+        let mut xored = [0; 1024];
+        XorDelta::xor(&transposed, &base, &mut xored);
+        assert!(xored.iter().all(|&x| x.leading_zeros() as usize >= 32 - W));
+
+        let mut packed = [0; 128 * W / size_of::<u32>()];
+        BitPacking::pack::<W>(&xored, &mut packed);
+
+        let mut unpacked = [0; 1024];
+        XorDelta::unxor_pack::<W>(&packed, &base, &mut unpacked);
+        assert_eq!(transposed, unpacked);
+    }
+}